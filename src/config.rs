@@ -0,0 +1,219 @@
+//! User configuration: named agency profiles saying where each agency's feeds and data live.
+//!
+//! The config is read from `$XDG_CONFIG_HOME/bus/config.toml` (or `~/.config/bus/config.toml`):
+//!
+//! ```toml
+//! default = "mmt"
+//!
+//! [agency.mmt]
+//! gtfs_url = "http://transitdata.cityofmadison.com/GTFS/mmt_gtfs.zip"
+//! trip_update_url = "http://transitdata.cityofmadison.com/TripUpdate/TripUpdates.json"
+//! data = "~/.bus/mmt"
+//!
+//! # Optional: how far (meters) and how fast (meters per second) to walk between stops.
+//! [walking]
+//! radius = 400
+//! speed = 1.2
+//! ```
+//!
+//! Without a config file, there is a single built-in profile for Madison Metro.
+//!
+//! The stops starred in `bus tui` are kept next to it, in `favorites.toml`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context};
+
+use serde::{Deserialize, Serialize};
+
+use crate::walking::Walking;
+
+/// The address of Madison Metro's trip updates, used by the built-in profile.
+pub const TRIP_UPDATE_URL: &str =
+    "http://transitdata.cityofmadison.com/TripUpdate/TripUpdates.json";
+
+/// The address of Madison Metro's schedule data, used by the built-in profile.
+pub const GTFS_DATA_URL: &str = "http://transitdata.cityofmadison.com/GTFS/mmt_gtfs.zip";
+
+/// The name of the built-in profile.
+pub const DEFAULT_AGENCY: &str = "mmt";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Agency {
+    /// Where `update` downloads the static GTFS zip from.
+    pub gtfs_url: String,
+
+    /// The GTFS-RT trip updates feed (protobuf or JSON), if the agency has one.
+    pub trip_update_url: Option<String>,
+
+    /// Where the GTFS data is kept: an extracted directory or a `.zip`. A leading `~` is expanded.
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// The profile to use when `--agency` is not given.
+    default: Option<String>,
+
+    /// Agency profiles by name.
+    #[serde(default)]
+    agency: BTreeMap<String, Agency>,
+
+    /// How far and fast to walk between stops when planning journeys.
+    #[serde(default)]
+    walking: Walking,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut agency = BTreeMap::new();
+        agency.insert(
+            DEFAULT_AGENCY.to_owned(),
+            Agency {
+                gtfs_url: GTFS_DATA_URL.to_owned(),
+                trip_update_url: Some(TRIP_UPDATE_URL.to_owned()),
+                data: "~/.bus".to_owned(),
+            },
+        );
+
+        Self {
+            default: Some(DEFAULT_AGENCY.to_owned()),
+            agency,
+            walking: Walking::default(),
+        }
+    }
+}
+
+impl Config {
+    /// The directory the config file and favorites live in.
+    pub fn dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("bus"))
+    }
+
+    /// Where the config file lives.
+    pub fn path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Read the config file, or use the built-in profile if there isn't one.
+    pub fn load() -> Result<Self, anyhow::Error> {
+        match Self::path() {
+            Some(path) if path.is_file() => {
+                let config = fs::read_to_string(&path)
+                    .with_context(|| format!("Unable to read {}", path.display()))?;
+                let config: Self = toml::from_str(&config)
+                    .with_context(|| format!("Invalid config {}", path.display()))?;
+                config
+                    .walking
+                    .check()
+                    .with_context(|| format!("Invalid config {}", path.display()))?;
+                Ok(config)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// How far and fast to walk between stops.
+    pub fn walking(&self) -> Walking {
+        self.walking
+    }
+
+    /// The name of the profile to use: the given one, or the default one if no name is given.
+    pub fn agency_name<'a>(&'a self, name: Option<&'a str>) -> Result<&'a str, anyhow::Error> {
+        Ok(match (name, &self.default) {
+            (Some(name), _) => name,
+            (None, Some(default)) => default.as_str(),
+            (None, None) if self.agency.len() == 1 => self.agency.keys().next().unwrap(),
+            (None, None) => bail!(
+                "Several agencies are configured; pick one with --agency or set `default` in \
+                 the config."
+            ),
+        })
+    }
+
+    /// Get the named profile, or the default one if no name is given.
+    pub fn agency(&self, name: Option<&str>) -> Result<Agency, anyhow::Error> {
+        let name = self.agency_name(name)?;
+
+        let mut agency = self.agency.get(name).cloned().ok_or_else(|| {
+            anyhow!(
+                "No agency profile named {}. Known agencies: {}",
+                name,
+                self.agency.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+
+        if let Some(rest) = agency.data.strip_prefix('~') {
+            let home = std::env::var("HOME").context("HOME is not set")?;
+            agency.data = format!("{}{}", home, rest);
+        }
+
+        Ok(agency)
+    }
+}
+
+/// The stops starred in `bus tui`, by agency profile name.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Favorites {
+    #[serde(flatten)]
+    stops: BTreeMap<String, Vec<String>>,
+}
+
+impl Favorites {
+    /// Where the favorites are kept.
+    pub fn path() -> Option<PathBuf> {
+        Config::dir().map(|dir| dir.join("favorites.toml"))
+    }
+
+    /// Read the favorites, or start with none if there are none yet.
+    pub fn load() -> Result<Self, anyhow::Error> {
+        match Self::path() {
+            Some(path) if path.is_file() => {
+                let favorites = fs::read_to_string(&path)
+                    .with_context(|| format!("Unable to read {}", path.display()))?;
+                toml::from_str(&favorites)
+                    .with_context(|| format!("Invalid favorites {}", path.display()))
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Write the favorites back.
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let path = Self::path().ok_or_else(|| anyhow!("HOME is not set"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("Unable to write {}", path.display()))
+    }
+
+    /// The IDs of the agency's favorite stops, in the order they were starred.
+    pub fn stops(&self, agency: &str) -> &[String] {
+        self.stops.get(agency).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains(&self, agency: &str, stop_id: &str) -> bool {
+        self.stops(agency)
+            .iter()
+            .any(|favorite| favorite == stop_id)
+    }
+
+    /// Star the stop, or unstar it if it already is. Returns whether it is now a favorite.
+    pub fn toggle(&mut self, agency: &str, stop_id: &str) -> bool {
+        let stops = self.stops.entry(agency.to_owned()).or_default();
+        if let Some(i) = stops.iter().position(|favorite| favorite == stop_id) {
+            stops.remove(i);
+            false
+        } else {
+            stops.push(stop_id.to_owned());
+            true
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Points on the earth, the distances and directions between them, and finding the ones near a
+//! place.
+
+use std::collections::HashMap;
+
+/// The mean radius of the earth, in meters.
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// The length of a degree of latitude, in meters.
+const METERS_PER_DEGREE: f64 = EARTH_RADIUS * std::f64::consts::PI / 180.0;
+
+/// The size of a [`GridIndex`] cell, in degrees. About 550m north to south.
+const CELL_DEGREES: f64 = 0.005;
+
+/// A latitude and longitude, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatLon {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LatLon {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// The great-circle distance to `other` in meters, by the haversine formula.
+    pub fn distance_to(self, other: LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    /// The initial bearing to `other`, in degrees clockwise from north.
+    pub fn bearing_to(self, other: LatLon) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+}
+
+/// The nearest of the eight compass points to a bearing in degrees, e.g. `NE`.
+pub fn compass_point(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    POINTS[((bearing.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+/// Parse a distance such as `400m`, `1.5km` or `0.25mi` into meters. A bare number is in meters.
+pub fn parse_distance(distance: &str) -> Option<f64> {
+    let distance = distance.trim();
+    let (number, unit) = match distance.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => distance.split_at(i),
+        None => (distance, "m"),
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    let meters = match unit.trim() {
+        "m" => number,
+        "km" => number * 1000.0,
+        "mi" => number * 1609.344,
+        "ft" => number * 0.3048,
+        _ => return None,
+    };
+
+    if meters.is_finite() && meters >= 0.0 {
+        Some(meters)
+    } else {
+        None
+    }
+}
+
+/// Items at places on the earth, bucketed into a grid so that the ones near a place can be found
+/// without looking at all of them.
+#[derive(Debug, Clone)]
+pub struct GridIndex<T> {
+    cells: HashMap<(i64, i64), Vec<(LatLon, T)>>,
+}
+
+impl<T> Default for GridIndex<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> GridIndex<T> {
+    fn cell(at: LatLon) -> (i64, i64) {
+        (
+            (at.lat / CELL_DEGREES).floor() as i64,
+            (at.lon / CELL_DEGREES).floor() as i64,
+        )
+    }
+
+    pub fn insert(&mut self, at: LatLon, item: T) {
+        self.cells
+            .entry(Self::cell(at))
+            .or_default()
+            .push((at, item));
+    }
+
+    /// The items within `radius` meters of `center` with their distances, nearest first.
+    pub fn within(&self, center: LatLon, radius: f64) -> Vec<(&T, f64)> {
+        // The box of cells around the circle. Longitude lines get closer towards the poles.
+        let dlat = radius / METERS_PER_DEGREE;
+        let dlon = radius / (METERS_PER_DEGREE * center.lat.to_radians().cos().max(1e-6));
+        let (south, west) = Self::cell(LatLon::new(center.lat - dlat, center.lon - dlon));
+        let (north, east) = Self::cell(LatLon::new(center.lat + dlat, center.lon + dlon));
+
+        let cells = (north - south + 1).saturating_mul(east - west + 1);
+        let candidates: Vec<&(LatLon, T)> = if cells as usize > self.cells.len() {
+            // A huge radius: cheaper to look at every cell.
+            self.cells.values().flatten().collect()
+        } else {
+            (south..=north)
+                .flat_map(|lat| (west..=east).map(move |lon| (lat, lon)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flatten()
+                .collect()
+        };
+
+        let mut found: Vec<_> = candidates
+            .into_iter()
+            .map(|(at, item)| (item, center.distance_to(*at)))
+            .filter(|(_, distance)| *distance <= radius)
+            .collect();
+        found.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        found
+    }
+}
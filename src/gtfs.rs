@@ -0,0 +1,828 @@
+//! Typed records from the static GTFS feed, and reading them out of a directory or `.zip`.
+
+use std::fs;
+use std::io::Read;
+use std::path;
+
+use anyhow::{anyhow, bail};
+
+use bitflags::bitflags;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Weekday};
+
+use csv::ReaderBuilder;
+
+use serde::Deserialize;
+
+use crate::geo::LatLon;
+
+/// A trip from `trips.txt`: one run of a bus along a route.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Trip {
+    pub route_id: String,
+    /// Filled in from `routes.txt` if the feed's `trips.txt` doesn't have it.
+    #[serde(default)]
+    pub route_short_name: String,
+    pub service_id: String,
+    pub trip_id: String,
+    #[serde(default)]
+    pub trip_headsign: String,
+    #[serde(default)]
+    pub direction_id: String,
+    #[serde(default)]
+    pub trip_direction_name: String,
+    #[serde(default)]
+    pub block_id: String,
+    #[serde(default)]
+    pub shape_id: String,
+    #[serde(default)]
+    pub shape_code: String,
+    #[serde(default)]
+    pub trip_type: String,
+    #[serde(default)]
+    pub trip_sort: String,
+    #[serde(default)]
+    pub wheelchair_accessible: String,
+    #[serde(default)]
+    pub bikes_allowed: String,
+}
+
+impl Trip {
+    /// Whether the bus can carry a rider in a wheelchair.
+    pub fn wheelchair(&self) -> Accessibility {
+        Accessibility::from_code(&self.wheelchair_accessible)
+    }
+
+    /// Whether riders may bring bikes.
+    pub fn bikes(&self) -> Accessibility {
+        Accessibility::from_code(&self.bikes_allowed)
+    }
+}
+
+/// Whether a trip or stop allows something, as given by fields like `wheelchair_accessible`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accessibility {
+    /// The feed doesn't say.
+    Unknown,
+    Yes,
+    No,
+}
+
+impl Accessibility {
+    pub fn from_code(code: &str) -> Self {
+        match code.trim() {
+            "1" => Accessibility::Yes,
+            "2" => Accessibility::No,
+            _ => Accessibility::Unknown,
+        }
+    }
+
+    /// A stable lowercase name, e.g. for machine-readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Accessibility::Unknown => "unknown",
+            Accessibility::Yes => "yes",
+            Accessibility::No => "no",
+        }
+    }
+}
+
+/// An agency from `agency.txt`. Only the fields needed here are read.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Agency {
+    #[serde(default)]
+    pub agency_id: String,
+    pub agency_name: String,
+    #[serde(default)]
+    pub agency_url: String,
+    /// The IANA name of the zone the agency's times are in, e.g. `America/Chicago`.
+    pub agency_timezone: String,
+}
+
+/// A row of `stops.txt`, as written in the file. Only the ID is always required by GTFS.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopRaw {
+    pub stop_id: String,
+    #[serde(default)]
+    pub stop_code: String,
+    #[serde(default)]
+    pub stop_name: String,
+    #[serde(default)]
+    pub stop_desc: String,
+    #[serde(default)]
+    pub stop_lat: String,
+    #[serde(default)]
+    pub stop_lon: String,
+    #[serde(default)]
+    pub agency_id: String,
+    #[serde(default)]
+    pub jurisdiction_id: String,
+    #[serde(default)]
+    pub location_type: String,
+    #[serde(default)]
+    pub parent_station: String,
+    #[serde(default)]
+    pub relative_position: String,
+    #[serde(default)]
+    pub cardinal_direction: String,
+    #[serde(default)]
+    pub wheelchair_boarding: String,
+    #[serde(default)]
+    pub primary_street: String,
+    #[serde(default)]
+    pub address_range: String,
+    #[serde(default)]
+    pub cross_location: String,
+    #[serde(default)]
+    pub platform_code: String,
+}
+
+/// A stop (or station) from `stops.txt`.
+#[derive(Debug, Clone)]
+pub struct Stop {
+    pub stop_id: String,
+    pub stop_code: String,
+    pub stop_name: String,
+    pub stop_desc: String,
+    /// In degrees, if the feed gives it.
+    pub stop_lat: Option<f64>,
+    pub stop_lon: Option<f64>,
+    pub agency_id: String,
+    pub jurisdiction_id: String,
+    pub location_type: String,
+    pub parent_station: String,
+    pub relative_position: String,
+    pub cardinal_direction: String,
+    pub wheelchair_boarding: String,
+    pub primary_street: String,
+    pub address_range: String,
+    pub cross_location: String,
+    /// The platform within a station, e.g. `G`, if the feed gives it.
+    pub platform_code: String,
+}
+
+impl Stop {
+    pub fn from_raw(raw: StopRaw) -> Self {
+        Self {
+            stop_id: raw.stop_id,
+            stop_code: raw.stop_code,
+            stop_name: raw.stop_name,
+            stop_desc: raw.stop_desc,
+            stop_lat: raw.stop_lat.trim().parse().ok(),
+            stop_lon: raw.stop_lon.trim().parse().ok(),
+            agency_id: raw.agency_id,
+            jurisdiction_id: raw.jurisdiction_id,
+            location_type: raw.location_type,
+            parent_station: raw.parent_station,
+            relative_position: raw.relative_position,
+            cardinal_direction: raw.cardinal_direction,
+            wheelchair_boarding: raw.wheelchair_boarding,
+            primary_street: raw.primary_street,
+            address_range: raw.address_range,
+            cross_location: raw.cross_location,
+            platform_code: raw.platform_code,
+        }
+    }
+
+    /// Where the stop is, if the feed says.
+    pub fn location(&self) -> Option<LatLon> {
+        Some(LatLon::new(self.stop_lat?, self.stop_lon?))
+    }
+
+    /// Whether buses stop here, as opposed to this being a whole station, an entrance, or some
+    /// other place in a station.
+    pub fn is_stop(&self) -> bool {
+        self.location_type.is_empty() || self.location_type == "0"
+    }
+
+    /// Whether this is a whole station, grouping the stops in it.
+    pub fn is_station(&self) -> bool {
+        self.location_type == "1"
+    }
+
+    /// A short name telling the stop apart from the others in its station: its platform, or
+    /// failing that its code or ID.
+    pub fn platform(&self) -> &str {
+        [&self.platform_code, &self.stop_code, &self.stop_id]
+            .iter()
+            .find(|label| !label.is_empty())
+            .map_or("", |label| label.as_str())
+    }
+}
+
+/// A row of `routes.txt`, as written in the file. Only the ID and type are required by GTFS.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteRaw {
+    pub route_id: String,
+    #[serde(default)]
+    pub agency_id: String,
+    #[serde(default)]
+    pub route_short_name: String,
+    #[serde(default)]
+    pub route_long_name: String,
+    #[serde(default)]
+    pub route_desc: String,
+    pub route_type: String,
+    #[serde(default)]
+    pub route_url: String,
+    #[serde(default)]
+    pub route_color: String,
+    #[serde(default)]
+    pub route_text_color: String,
+    #[serde(default)]
+    pub route_sort_order: String,
+}
+
+/// The kind of vehicle serving a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteType {
+    Tram,
+    Subway,
+    Rail,
+    Bus,
+    Ferry,
+    CableTram,
+    AerialLift,
+    Funicular,
+    Trolleybus,
+    Monorail,
+    /// One of the extended route types some feeds use.
+    Other(u16),
+}
+
+impl RouteType {
+    pub fn from_code(code: &str) -> Self {
+        match code.trim().parse::<u16>().unwrap_or(3) {
+            0 => RouteType::Tram,
+            1 => RouteType::Subway,
+            2 => RouteType::Rail,
+            3 => RouteType::Bus,
+            4 => RouteType::Ferry,
+            5 => RouteType::CableTram,
+            6 => RouteType::AerialLift,
+            7 => RouteType::Funicular,
+            11 => RouteType::Trolleybus,
+            12 => RouteType::Monorail,
+            other => RouteType::Other(other),
+        }
+    }
+
+    /// A stable lowercase name, e.g. for machine-readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            RouteType::Tram => "tram",
+            RouteType::Subway => "subway",
+            RouteType::Rail => "rail",
+            RouteType::Bus => "bus",
+            RouteType::Ferry => "ferry",
+            RouteType::CableTram => "cable_tram",
+            RouteType::AerialLift => "aerial_lift",
+            RouteType::Funicular => "funicular",
+            RouteType::Trolleybus => "trolleybus",
+            RouteType::Monorail => "monorail",
+            RouteType::Other(_) => "other",
+        }
+    }
+}
+
+/// A route from `routes.txt`.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub route_id: String,
+    pub agency_id: String,
+    pub route_short_name: String,
+    pub route_long_name: String,
+    pub route_desc: String,
+    pub route_type: RouteType,
+    pub route_url: String,
+    /// Hex color without the leading `#`, e.g. `FF0000`.
+    pub route_color: Option<String>,
+    pub route_text_color: Option<String>,
+    pub route_sort_order: Option<u32>,
+}
+
+impl Route {
+    pub fn from_raw(raw: RouteRaw) -> Self {
+        let color = |color: String| Some(color).filter(|color| !color.is_empty());
+
+        Self {
+            route_id: raw.route_id,
+            agency_id: raw.agency_id,
+            route_short_name: raw.route_short_name,
+            route_long_name: raw.route_long_name,
+            route_desc: raw.route_desc,
+            route_type: RouteType::from_code(&raw.route_type),
+            route_url: raw.route_url,
+            route_color: color(raw.route_color),
+            route_text_color: color(raw.route_text_color),
+            route_sort_order: raw.route_sort_order.parse().ok(),
+        }
+    }
+
+    /// What riders call the route: its short name, or its long name if it has no short one.
+    pub fn name(&self) -> &str {
+        if self.route_short_name.is_empty() {
+            &self.route_long_name
+        } else {
+            &self.route_short_name
+        }
+    }
+}
+
+/// A row of `stop_times.txt`, as written in the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopTimeRaw {
+    pub trip_id: String,
+    pub stop_sequence: String,
+    pub stop_id: String,
+    #[serde(default)]
+    pub pickup_type: String,
+    #[serde(default)]
+    pub drop_off_type: String,
+    pub arrival_time: String,
+    pub departure_time: String,
+    #[serde(default)]
+    pub timepoint: String,
+    #[serde(default)]
+    pub stop_headsign: String,
+    #[serde(default)]
+    pub shape_dist_traveled: String,
+}
+
+/// A time of day relative to the start of a GTFS service day. Service days can run past
+/// midnight, so trips that belong to the previous day's service can be `24:00:00` or later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServiceTime(u32); // seconds since the start of the service day
+
+impl ServiceTime {
+    /// Parse a GTFS `H:MM:SS` time. The hours may be 24 or more.
+    pub fn parse(time: &str) -> Option<Self> {
+        let mut parts = time.trim().splitn(3, ':');
+        let hours: u32 = parts.next()?.parse().ok()?;
+        let minutes: u32 = parts.next()?.parse().ok()?;
+        let seconds: u32 = parts.next()?.parse().ok()?;
+
+        if minutes >= 60 || seconds >= 60 {
+            return None;
+        }
+        hours
+            .checked_mul(3600)?
+            .checked_add(minutes * 60 + seconds)
+            .map(ServiceTime)
+    }
+
+    /// The time elapsed since the start of the service day.
+    pub fn since_start_of_day(self) -> chrono::Duration {
+        chrono::Duration::seconds(self.0 as i64)
+    }
+
+    /// The actual date and time this refers to on the service day `day`.
+    pub fn on(self, day: NaiveDate) -> NaiveDateTime {
+        day.and_hms_opt(0, 0, 0).unwrap() + self.since_start_of_day()
+    }
+}
+
+/// A trip stopping at a stop, from `stop_times.txt`.
+#[derive(Debug, Clone)]
+pub struct StopTime {
+    pub trip_id: String,
+    pub stop_sequence: String,
+    pub stop_id: String,
+    pub pickup_type: String,
+    pub drop_off_type: String,
+    /// GTFS allows times to be left out for stops that are not timepoints.
+    pub arrival_time: Option<ServiceTime>,
+    pub departure_time: Option<ServiceTime>,
+    pub timepoint: String,
+    pub stop_headsign: String,
+    pub shape_dist_traveled: String,
+}
+
+impl StopTime {
+    /// If only one of the arrival and departure times is given, it is used for both.
+    pub fn from_raw(raw: StopTimeRaw) -> Self {
+        let arrival_time = ServiceTime::parse(&raw.arrival_time);
+        let departure_time = ServiceTime::parse(&raw.departure_time);
+
+        Self {
+            trip_id: raw.trip_id,
+            stop_sequence: raw.stop_sequence,
+            stop_id: raw.stop_id,
+            pickup_type: raw.pickup_type,
+            drop_off_type: raw.drop_off_type,
+            arrival_time: arrival_time.or(departure_time),
+            departure_time: departure_time.or(arrival_time),
+            timepoint: raw.timepoint,
+            stop_headsign: raw.stop_headsign,
+            shape_dist_traveled: raw.shape_dist_traveled,
+        }
+    }
+
+    /// The `stop_sequence` as a number, for putting a trip's stops in order.
+    pub fn sequence(&self) -> u32 {
+        self.stop_sequence.parse().unwrap_or(0)
+    }
+}
+
+/// A row of `calendar.txt`, as written in the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalendarRaw {
+    pub service_id: String,
+    #[serde(default)]
+    pub service_name: String,
+    pub monday: String,
+    pub tuesday: String,
+    pub wednesday: String,
+    pub thursday: String,
+    pub friday: String,
+    pub saturday: String,
+    pub sunday: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+bitflags! {
+    /// The days of the week a service runs on.
+    #[derive(Deserialize)]
+    pub struct Days: u8 {
+        const MONDAY = 1 << 0;
+        const TUESDAY = 1 << 1;
+        const WEDNESDAY = 1 << 2;
+        const THURSDAY = 1 << 3;
+        const FRIDAY = 1 << 4;
+        const SATURDAY = 1 << 5;
+        const SUNDAY = 1 << 6;
+    }
+}
+
+impl Days {
+    pub fn from_weekday(wd: Weekday) -> Self {
+        match wd {
+            Weekday::Sun => Days::SUNDAY,
+            Weekday::Mon => Days::MONDAY,
+            Weekday::Tue => Days::TUESDAY,
+            Weekday::Wed => Days::WEDNESDAY,
+            Weekday::Thu => Days::THURSDAY,
+            Weekday::Fri => Days::FRIDAY,
+            Weekday::Sat => Days::SATURDAY,
+        }
+    }
+}
+
+/// A service: the days on which a set of trips runs, from `calendar.txt` and
+/// `calendar_dates.txt`.
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    pub service_id: String,
+    pub service_name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub days: Days,
+    pub exceptions: Vec<CalendarDate>,
+}
+
+impl Calendar {
+    /// A service with a regular weekly schedule. Exceptions are added afterwards.
+    pub fn from_calendar(calendar: CalendarRaw) -> Result<Self, anyhow::Error> {
+        let mut days = Days::empty();
+        if calendar.sunday == "1" {
+            days |= Days::SUNDAY;
+        }
+        if calendar.monday == "1" {
+            days |= Days::MONDAY;
+        }
+        if calendar.tuesday == "1" {
+            days |= Days::TUESDAY;
+        }
+        if calendar.wednesday == "1" {
+            days |= Days::WEDNESDAY;
+        }
+        if calendar.thursday == "1" {
+            days |= Days::THURSDAY;
+        }
+        if calendar.friday == "1" {
+            days |= Days::FRIDAY;
+        }
+        if calendar.saturday == "1" {
+            days |= Days::SATURDAY;
+        }
+
+        Ok(Self {
+            start_date: parse_date(&calendar.start_date, &calendar.service_id)?,
+            end_date: parse_date(&calendar.end_date, &calendar.service_id)?,
+            service_id: calendar.service_id,
+            service_name: calendar.service_name,
+            days,
+            exceptions: vec![],
+        })
+    }
+
+    /// A service defined only through `calendar_dates.txt`, which the GTFS spec allows. It has no
+    /// regular weekly schedule, so it only runs on its added dates.
+    pub fn from_exceptions_only(service_id: String, date: NaiveDate) -> Self {
+        Self {
+            service_id,
+            service_name: String::new(),
+            start_date: date,
+            end_date: date,
+            days: Days::empty(),
+            exceptions: vec![],
+        }
+    }
+
+    /// Does this service run on the given service day? An exception for that date in
+    /// `calendar_dates.txt` overrides the regular weekly schedule either way.
+    pub fn runs_on(&self, day: NaiveDate) -> bool {
+        if let Some(exception) = self.exceptions.iter().find(|ex| ex.date == day) {
+            exception.exception_type == ExceptionType::Added
+        } else {
+            self.start_date <= day
+                && day <= self.end_date
+                && self.days.contains(Days::from_weekday(day.weekday()))
+        }
+    }
+}
+
+/// A row of `calendar_dates.txt`, as written in the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalendarDateRaw {
+    pub date: String,
+    pub exception_type: String,
+    pub service_id: String,
+}
+
+/// Whether a service is added or removed on a particular date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExceptionType {
+    Added,
+    Removed,
+}
+
+/// An exception to a service's regular schedule, from `calendar_dates.txt`.
+#[derive(Debug, Clone)]
+pub struct CalendarDate {
+    pub date: NaiveDate,
+    pub exception_type: ExceptionType,
+    pub service_id: String,
+}
+
+impl CalendarDate {
+    /// Exception type `1` means added and `2` means removed.
+    pub fn from_raw(raw: CalendarDateRaw) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            date: parse_date(&raw.date, &raw.service_id)?,
+            exception_type: if raw.exception_type == "1" {
+                ExceptionType::Added
+            } else {
+                ExceptionType::Removed
+            },
+            service_id: raw.service_id,
+        })
+    }
+}
+
+/// Parse a GTFS `YYYYMMDD` date of the given service.
+fn parse_date(date: &str, service_id: &str) -> Result<NaiveDate, anyhow::Error> {
+    NaiveDate::parse_from_str(date.trim(), "%Y%m%d")
+        .map_err(|_| anyhow!("Bad date {:?} for service {}", date, service_id))
+}
+
+/// A row of `transfers.txt`, as written in the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransferRaw {
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    #[serde(default)]
+    pub transfer_type: String,
+    #[serde(default)]
+    pub min_transfer_time: String,
+}
+
+/// How a rider can transfer between two stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferType {
+    /// A recommended transfer point.
+    Recommended,
+    /// The departing bus waits for the arriving one.
+    Timed,
+    /// The transfer takes at least `min_transfer_time`.
+    MinimumTime,
+    /// Riders cannot transfer here.
+    NotPossible,
+}
+
+/// A transfer between two stops (or at one stop), from `transfers.txt`.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    pub transfer_type: TransferType,
+    /// In seconds.
+    pub min_transfer_time: Option<u32>,
+}
+
+impl Transfer {
+    /// Transfer types other than `1`, `2` and `3` (including in-seat transfers) are taken as
+    /// recommended.
+    pub fn from_raw(raw: TransferRaw) -> Self {
+        Self {
+            from_stop_id: raw.from_stop_id,
+            to_stop_id: raw.to_stop_id,
+            transfer_type: match raw.transfer_type.trim() {
+                "1" => TransferType::Timed,
+                "2" => TransferType::MinimumTime,
+                "3" => TransferType::NotPossible,
+                _ => TransferType::Recommended,
+            },
+            min_transfer_time: raw.min_transfer_time.trim().parse().ok(),
+        }
+    }
+}
+
+/// Where the GTFS feed lives: either an extracted directory or a `.zip` archive.
+pub enum GtfsSource {
+    Dir(path::PathBuf),
+    Zip(zip::ZipArchive<fs::File>),
+}
+
+impl GtfsSource {
+    /// Open the feed at `data`. A file is taken to be a zip archive, anything else a directory.
+    pub fn open(data: &str) -> Result<Self, anyhow::Error> {
+        let path = path::PathBuf::from(data);
+        if !path.exists() {
+            bail!(
+                "No schedule data at {}. Run the `update` command to download it.",
+                data
+            );
+        } else if path.is_file() {
+            Ok(GtfsSource::Zip(zip::ZipArchive::new(fs::File::open(
+                &path,
+            )?)?))
+        } else {
+            Ok(GtfsSource::Dir(path))
+        }
+    }
+
+    /// Find the path of the given feed file inside the archive.
+    fn find_member(archive: &zip::ZipArchive<fs::File>, name: &str) -> Option<String> {
+        archive
+            .file_names()
+            .find(|f| path::Path::new(f).file_name() == Some(name.as_ref()))
+            .map(String::from)
+    }
+
+    /// Does the feed contain the given file? Several GTFS files are optional.
+    pub fn has(&self, name: &str) -> bool {
+        match self {
+            GtfsSource::Dir(dir) => dir.join(name).is_file(),
+            GtfsSource::Zip(archive) => Self::find_member(archive, name).is_some(),
+        }
+    }
+
+    /// Get a CSV reader for the given feed file (e.g. `stops.txt`). Zip members are streamed
+    /// straight out of the archive. Some agencies nest the files in a folder inside the zip, so
+    /// we match on the file name alone.
+    pub fn reader(&mut self, name: &str) -> Result<csv::Reader<Box<dyn Read + '_>>, anyhow::Error> {
+        let reader: Box<dyn Read + '_> = match self {
+            GtfsSource::Dir(dir) => Box::new(fs::File::open(dir.join(name))?),
+            GtfsSource::Zip(archive) => {
+                let member = Self::find_member(archive, name)
+                    .ok_or_else(|| anyhow!("{} not found in GTFS archive", name))?;
+                Box::new(archive.by_name(&member)?)
+            }
+        };
+
+        Ok(ReaderBuilder::new().has_headers(true).from_reader(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn exception(service_id: &str, date: &str, exception_type: &str) -> CalendarDate {
+        CalendarDate::from_raw(CalendarDateRaw {
+            date: date.replace('-', ""),
+            exception_type: exception_type.into(),
+            service_id: service_id.into(),
+        })
+        .unwrap()
+    }
+
+    /// Mondays to Fridays in 2026.
+    fn weekdays() -> Calendar {
+        let on = || "1".to_string();
+        let off = || "0".to_string();
+        Calendar::from_calendar(CalendarRaw {
+            service_id: "WKD".into(),
+            service_name: "Weekday".into(),
+            monday: on(),
+            tuesday: on(),
+            wednesday: on(),
+            thursday: on(),
+            friday: on(),
+            saturday: off(),
+            sunday: off(),
+            start_date: "20260101".into(),
+            end_date: "20261231".into(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn runs_on_regular_days() {
+        let calendar = weekdays();
+        assert!(calendar.runs_on(date("2026-10-16"))); // Friday
+        assert!(!calendar.runs_on(date("2026-10-17"))); // Saturday
+        assert!(!calendar.runs_on(date("2025-12-31"))); // Wednesday, before the start
+        assert!(!calendar.runs_on(date("2027-01-01"))); // Friday, after the end
+    }
+
+    #[test]
+    fn removed_on_a_regular_day() {
+        let mut calendar = weekdays();
+        calendar
+            .exceptions
+            .push(exception("WKD", "2026-11-26", "2"));
+        assert!(!calendar.runs_on(date("2026-11-26")));
+        assert!(calendar.runs_on(date("2026-11-25")));
+        assert!(calendar.runs_on(date("2026-11-27")));
+    }
+
+    #[test]
+    fn added_outside_the_weekly_pattern() {
+        let mut calendar = weekdays();
+        calendar
+            .exceptions
+            .push(exception("WKD", "2026-10-17", "1"));
+        calendar
+            .exceptions
+            .push(exception("WKD", "2027-01-02", "1"));
+        assert!(calendar.runs_on(date("2026-10-17")));
+        assert!(calendar.runs_on(date("2027-01-02")));
+        assert!(!calendar.runs_on(date("2026-10-18")));
+    }
+
+    #[test]
+    fn bad_dates() {
+        let mut raw = CalendarRaw {
+            service_id: "WKD".into(),
+            service_name: String::new(),
+            monday: "1".into(),
+            tuesday: "1".into(),
+            wednesday: "1".into(),
+            thursday: "1".into(),
+            friday: "1".into(),
+            saturday: "0".into(),
+            sunday: "0".into(),
+            start_date: "2026-01-01".into(),
+            end_date: "20261231".into(),
+        };
+        assert!(Calendar::from_calendar(raw.clone()).is_err());
+        raw.start_date = "20260101".into();
+        assert!(Calendar::from_calendar(raw).is_ok());
+
+        assert!(CalendarDate::from_raw(CalendarDateRaw {
+            date: "tomorrow".into(),
+            exception_type: "1".into(),
+            service_id: "WKD".into(),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn only_in_calendar_dates() {
+        let mut calendar = Calendar::from_exceptions_only("XMAS".into(), date("2026-12-25"));
+        calendar
+            .exceptions
+            .push(exception("XMAS", "2026-12-25", "1"));
+        calendar
+            .exceptions
+            .push(exception("XMAS", "2026-12-26", "1"));
+        assert!(calendar.runs_on(date("2026-12-25")));
+        assert!(calendar.runs_on(date("2026-12-26")));
+        assert!(!calendar.runs_on(date("2026-12-24")));
+        assert!(!calendar.runs_on(date("2027-01-01")));
+    }
+
+    #[test]
+    fn service_times() {
+        assert_eq!(
+            ServiceTime::parse("8:05:30").map(ServiceTime::since_start_of_day),
+            Some(chrono::Duration::seconds(8 * 3600 + 5 * 60 + 30))
+        );
+        assert_eq!(
+            ServiceTime::parse("25:00:00").map(ServiceTime::since_start_of_day),
+            Some(chrono::Duration::hours(25))
+        );
+        assert_eq!(ServiceTime::parse("8:60:00"), None);
+        assert_eq!(ServiceTime::parse(""), None);
+        // Too many hours to count in seconds.
+        assert_eq!(ServiceTime::parse("9999999:00:00"), None);
+        assert_eq!(ServiceTime::parse("1193046:28:16"), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Typed GTFS-Realtime messages, decoded from the binary protobuf feed.
+//!
+//! These follow `gtfs-realtime.proto`, but only the parts we use are declared. Anything else in the
+//! feed (vehicle positions, alerts, extensions) is skipped by the decoder.
+
+use prost::{Enumeration, Message};
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedMessage {
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedHeader {
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    /// Seconds since the epoch at which the feed was generated.
+    #[prost(uint64, optional, tag = "3")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedEntity {
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    #[prost(bool, optional, tag = "2")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    pub trip_update: Option<TripUpdate>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripUpdate {
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    #[prost(uint64, optional, tag = "4")]
+    pub timestamp: Option<u64>,
+    #[prost(int32, optional, tag = "5")]
+    pub delay: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub trip_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub start_time: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub start_date: Option<String>,
+    #[prost(
+        enumeration = "trip_descriptor::ScheduleRelationship",
+        optional,
+        tag = "4"
+    )]
+    pub schedule_relationship: Option<i32>,
+    #[prost(string, optional, tag = "5")]
+    pub route_id: Option<String>,
+    #[prost(uint32, optional, tag = "6")]
+    pub direction_id: Option<u32>,
+}
+
+pub mod trip_descriptor {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum ScheduleRelationship {
+        Scheduled = 0,
+        Added = 1,
+        Unscheduled = 2,
+        Canceled = 3,
+        Replacement = 5,
+        Duplicated = 6,
+        Deleted = 7,
+    }
+
+    impl ScheduleRelationship {
+        /// Parse the name used in JSON renderings of the feed, e.g. `CANCELED`.
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "SCHEDULED" => Some(Self::Scheduled),
+                "ADDED" => Some(Self::Added),
+                "UNSCHEDULED" => Some(Self::Unscheduled),
+                "CANCELED" => Some(Self::Canceled),
+                "REPLACEMENT" => Some(Self::Replacement),
+                "DUPLICATED" => Some(Self::Duplicated),
+                "DELETED" => Some(Self::Deleted),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeEvent {
+    /// Seconds relative to the schedule. Negative means early.
+    #[prost(int32, optional, tag = "1")]
+    pub delay: Option<i32>,
+    /// Absolute time in seconds since the epoch.
+    #[prost(int64, optional, tag = "2")]
+    pub time: Option<i64>,
+    #[prost(int32, optional, tag = "3")]
+    pub uncertainty: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeUpdate {
+    #[prost(uint32, optional, tag = "1")]
+    pub stop_sequence: Option<u32>,
+    #[prost(message, optional, tag = "2")]
+    pub arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    pub departure: Option<StopTimeEvent>,
+    #[prost(string, optional, tag = "4")]
+    pub stop_id: Option<String>,
+    #[prost(
+        enumeration = "stop_time_update::ScheduleRelationship",
+        optional,
+        tag = "5"
+    )]
+    pub schedule_relationship: Option<i32>,
+}
+
+pub mod stop_time_update {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum ScheduleRelationship {
+        Scheduled = 0,
+        Skipped = 1,
+        NoData = 2,
+        Unscheduled = 3,
+    }
+
+    impl ScheduleRelationship {
+        /// Parse the name used in JSON renderings of the feed, e.g. `SKIPPED`.
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name {
+                "SCHEDULED" => Some(Self::Scheduled),
+                "SKIPPED" => Some(Self::Skipped),
+                "NO_DATA" => Some(Self::NoData),
+                "UNSCHEDULED" => Some(Self::Unscheduled),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Decode a binary GTFS-RT feed.
+pub fn decode(bytes: &[u8]) -> Result<FeedMessage, prost::DecodeError> {
+    FeedMessage::decode(bytes)
+}
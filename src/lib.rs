@@ -0,0 +1,28 @@
+//! A schedule engine for GTFS transit feeds, with real-time updates.
+//!
+//! Load a feed with [`Data::read`], then ask it about a stop with [`Data::stop_sched`], follow one
+//! bus along its route with [`Data::trip_sched`], plan a journey between two stops with
+//! [`Data::plan`], or look for stops with [`Data::search`].
+//! Real-time delays come from [`realtime::fetch_real_time_data`] or, for data you already have,
+//! [`realtime::parse_real_time_feed`] and [`realtime::parse_real_time_data`].
+
+pub mod config;
+pub mod geo;
+pub mod gtfs;
+pub mod gtfs_rt;
+pub mod output;
+pub mod plan;
+pub mod realtime;
+pub mod schedule;
+pub mod search;
+pub mod timetable;
+pub mod trip;
+pub mod update;
+pub mod walking;
+pub mod when;
+
+pub use plan::{Journey, Leg, PlanTime};
+pub use realtime::RealTimeInfo;
+pub use schedule::{BusInfo, BusStatus, Data, FilterConfig, NearbyStop, RouteSummary, StopBusInfo};
+pub use timetable::Timetable;
+pub use trip::{TripInfo, TripStop};
@@ -1,624 +1,255 @@
-//! Reads bus info and answers questions about routes.
+//! Command-line interface to the `bus` schedule engine.
 
-use std::collections::HashMap;
-use std::fs;
-use std::path;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{IsTerminal, Write as _};
+use std::time::{Duration, Instant};
 
-use bitflags::bitflags;
-
-use chrono::{offset::Local, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
-
-use clap::clap_app;
+use anyhow::bail;
 
-use csv::ReaderBuilder;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 
-use anyhow::bail;
+use chrono_tz::Tz;
 
-use serde::Deserialize;
+use clap::clap_app;
 
-/// The address of the trip update.
-pub const TRIP_UPDATE_URL: &str =
-    "http://transitdata.cityofmadison.com/TripUpdate/TripUpdates.json";
+use bus::config::Config;
+use bus::geo::{self, LatLon};
+use bus::gtfs::Accessibility;
+use bus::output::{OutputFormat, StopRecord};
+use bus::realtime::{self, RealTimeInfo};
+use bus::{update, when, BusStatus, Data, FilterConfig, PlanTime};
 
-/// The address of the schedule data.
-pub const GTFS_DATA_URL: &str = "http://transitdata.cityofmadison.com/GTFS/mmt_gtfs.zip";
+mod tui;
 
 /// The default number of busses to show for a stop.
 pub const DEFAULT_N: usize = 10;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Deserialize)]
-struct Trip {
-    route_id: String,
-    route_short_name: String,
-    service_id: String,
-    trip_id: String,
-    trip_headsign: String,
-    direction_id: String,
-    trip_direction_name: String,
-    block_id: String,
-    shape_id: String,
-    shape_code: String,
-    trip_type: String,
-    trip_sort: String,
-    wheelchair_accessible: String,
-    bikes_allowed: String,
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone, Deserialize)]
-struct Stop {
-    stop_id: String,
-    stop_code: String,
-    stop_name: String,
-    stop_desc: String,
-    stop_lat: String,
-    stop_lon: String,
-    agency_id: String,
-    jurisdiction_id: String,
-    location_type: String,
-    parent_station: String,
-    relative_position: String,
-    cardinal_direction: String,
-    wheelchair_boarding: String,
-    primary_street: String,
-    address_range: String,
-    cross_location: String,
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone, Deserialize)]
-struct StopTimeRaw {
-    trip_id: String,
-    stop_sequence: String,
-    stop_id: String,
-    pickup_type: String,
-    drop_off_type: String,
-    arrival_time: String,
-    departure_time: String,
-    timepoint: String,
-    stop_headsign: String,
-    shape_dist_traveled: String,
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-struct StopTime {
-    trip_id: String,
-    stop_sequence: String,
-    stop_id: String,
-    pickup_type: String,
-    drop_off_type: String,
-    arrival_time: NaiveTime,
-    departure_time: NaiveTime,
-    timepoint: String,
-    stop_headsign: String,
-    shape_dist_traveled: String,
-}
-
-impl StopTime {
-    pub fn from_raw(raw: StopTimeRaw) -> Self {
-        Self {
-            trip_id: raw.trip_id,
-            stop_sequence: raw.stop_sequence,
-            stop_id: raw.stop_id,
-            pickup_type: raw.pickup_type,
-            drop_off_type: raw.drop_off_type,
-            arrival_time: NaiveTime::parse_from_str(&raw.arrival_time, "%k:%M:%S")
-                .unwrap_or_else(|_| NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
-            departure_time: NaiveTime::parse_from_str(&raw.departure_time, "%k:%M:%S")
-                .unwrap_or_else(|_| NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
-            timepoint: raw.timepoint,
-            stop_headsign: raw.stop_headsign,
-            shape_dist_traveled: raw.shape_dist_traveled,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct CalendarRaw {
-    service_id: String,
-    service_name: String,
-    monday: String,
-    tuesday: String,
-    wednesday: String,
-    thursday: String,
-    friday: String,
-    saturday: String,
-    sunday: String,
-    start_date: String,
-    end_date: String,
-}
-
-bitflags! {
-    #[derive(Deserialize)]
-    struct Days: u8 {
-        const MONDAY = 1 << 0;
-        const TUESDAY = 1 << 1;
-        const WEDNESDAY = 1 << 2;
-        const THURSDAY = 1 << 3;
-        const FRIDAY = 1 << 4;
-        const SATURDAY = 1 << 5;
-        const SUNDAY = 1 << 6;
-    }
-}
-
-impl Days {
-    pub fn from_weekday(wd: Weekday) -> Self {
-        match wd {
-            Weekday::Sun => Days::SUNDAY,
-            Weekday::Mon => Days::MONDAY,
-            Weekday::Tue => Days::TUESDAY,
-            Weekday::Wed => Days::WEDNESDAY,
-            Weekday::Thu => Days::THURSDAY,
-            Weekday::Fri => Days::FRIDAY,
-            Weekday::Sat => Days::SATURDAY,
-        }
-    }
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-struct Calendar {
-    service_id: String,
-    service_name: String,
-    start_date: NaiveDate,
-    end_date: NaiveDate,
-    days: Days,
-    exceptions: Vec<CalendarDate>,
-}
-
-impl Calendar {
-    pub fn from_calendar(calendar: CalendarRaw) -> Self {
-        let mut days = Days::empty();
-        if calendar.sunday == "1" {
-            days |= Days::SUNDAY;
-        }
-        if calendar.monday == "1" {
-            days |= Days::MONDAY;
-        }
-        if calendar.tuesday == "1" {
-            days |= Days::TUESDAY;
-        }
-        if calendar.wednesday == "1" {
-            days |= Days::WEDNESDAY;
-        }
-        if calendar.thursday == "1" {
-            days |= Days::THURSDAY;
-        }
-        if calendar.friday == "1" {
-            days |= Days::FRIDAY;
-        }
-        if calendar.saturday == "1" {
-            days |= Days::SATURDAY;
-        }
-
-        Self {
-            service_id: calendar.service_id,
-            service_name: calendar.service_name,
-            start_date: NaiveDate::parse_from_str(&calendar.start_date, "%Y%m%d")
-                .expect("Error parsing date"),
-            end_date: NaiveDate::parse_from_str(&calendar.end_date, "%Y%m%d")
-                .expect("Error parsing date"),
-            days,
-            exceptions: vec![],
-        }
-    }
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct CalendarDateRaw {
-    date: String,
-    exception_type: String,
-    service_id: String,
-}
+/// The default distance to look for stops near a place, in meters.
+pub const DEFAULT_RADIUS: f64 = 400.0;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum ExceptionType {
-    Added,
-    Removed,
-}
+/// The default number of seconds between fetches of the real-time data in `watch`.
+pub const DEFAULT_WATCH_INTERVAL: u64 = 30;
 
-#[derive(Debug, Clone)]
-struct CalendarDate {
-    date: NaiveDate,
-    exception_type: ExceptionType,
-    service_id: String,
-}
+/// How often `watch` redraws, in seconds, so that the countdowns keep up between fetches.
+const WATCH_REDRAW: u64 = 5;
 
-impl CalendarDate {
-    pub fn from_raw(raw: CalendarDateRaw) -> Self {
-        Self {
-            date: NaiveDate::parse_from_str(&raw.date, "%Y%m%d").expect("Error parsing date"),
-            exception_type: if raw.exception_type == "1" {
-                ExceptionType::Added
-            } else {
-                ExceptionType::Removed
-            },
-            service_id: raw.service_id,
+/// Fetch the real-time trip updates, if the agency has them. If that fails, we warn and carry on
+/// with only the static schedule.
+fn fetch_real_time_or_warn(trip_update_url: Option<&str>) -> RealTimeInfo {
+    match trip_update_url.map(realtime::fetch_real_time_data) {
+        Some(Ok(real_time)) => real_time,
+        Some(Err(e)) => {
+            eprintln!("WARNING: {}", e);
+            Default::default()
         }
+        None => Default::default(),
     }
 }
 
-struct StopBusInfo {
-    stop_name: String,
-    // (trip_short_name, headsign, departure_time, delay in seconds)
-    buses: Vec<(String, String, NaiveTime, Option<f64>)>,
-}
-
-struct FilterConfig<'s> {
-    /// Stop ID
-    stop_id: &'s str,
-
-    /// List buses at or after `after`
-    after: NaiveDateTime,
-
-    /// How many buses to list?
-    how_many: Option<usize>,
-
-    /// Which route to list? If none, list all.
-    route: Option<&'s str>,
-}
-
-impl<'s> FilterConfig<'s> {
-    pub fn new(stop_id: &'s str) -> FilterConfig<'s> {
-        Self {
-            stop_id,
-            after: Local::now().naive_local(),
-            how_many: None,
-            route: None,
-        }
-    }
-
-    pub fn after(self, after: NaiveDateTime) -> Self {
-        Self { after, ..self }
-    }
-
-    pub fn how_many(self, how_many: usize) -> Self {
-        Self {
-            how_many: Some(how_many),
-            ..self
-        }
-    }
-
-    pub fn route(self, route: &'s str) -> Self {
-        Self {
-            route: Some(route),
-            ..self
-        }
-    }
-}
-
-struct Data {
-    pub trips: HashMap<String, Trip>,               // by trip_id
-    pub stops: HashMap<String, Stop>,               // by stop_id
-    pub calendar: HashMap<String, Calendar>,        // by service_id
-    pub stop_times: HashMap<String, Vec<StopTime>>, // by stop_id
-}
-
-impl Data {
-    pub fn read(data_dir: &str) -> Result<Self, anyhow::Error> {
-        let mut calendar: HashMap<String, Calendar> = ReaderBuilder::new()
-            .has_headers(true)
-            .from_path(format!("{}/calendar.txt", data_dir))?
-            .deserialize()
-            .map(|r| r.expect("Unable to deserialize"))
-            .map(Calendar::from_calendar)
-            .map(|calendar| (calendar.service_id.clone(), calendar))
-            .collect();
-
-        for exception in ReaderBuilder::new()
-            .has_headers(true)
-            .from_path(format!("{}/calendar_dates.txt", data_dir))?
-            .deserialize()
-            .map(|r: Result<CalendarDateRaw, _>| r.expect("Unable to deserialize"))
-            .map(CalendarDate::from_raw)
-        {
-            calendar
-                .get_mut(&exception.service_id)
-                .expect("No such service.")
-                .exceptions
-                .push(exception);
-        }
-
-        let mut stop_times = HashMap::new();
-
-        for stop_time in ReaderBuilder::new()
-            .has_headers(true)
-            .from_path(format!("{}/stop_times.txt", data_dir))?
-            .deserialize()
-            .map(|r| r.expect("Unable to deserialize"))
-            .map(StopTime::from_raw)
-        {
-            stop_times
-                .entry(stop_time.stop_id.clone())
-                .or_insert(vec![])
-                .push(stop_time);
-        }
-
-        Ok(Self {
-            trips: ReaderBuilder::new()
-                .has_headers(true)
-                .from_path(format!("{}/trips.txt", data_dir))?
-                .deserialize()
-                .map(|r| r.expect("Unable to deserialize"))
-                .map(|trip: Trip| (trip.trip_id.clone(), trip))
-                .collect(),
-            stops: ReaderBuilder::new()
-                .has_headers(true)
-                .from_path(format!("{}/stops.txt", data_dir))?
-                .deserialize()
-                .map(|r| r.expect("Unable to deserialize"))
-                .map(|stop: Stop| (stop.stop_id.clone(), stop))
-                .collect(),
-            stop_times,
-            calendar,
-        })
-    }
-
-    /// Get buses at the stop matching the given filter and the real-time delay info.
-    pub fn stop_sched(
-        &self,
-        conf: FilterConfig,
-        real_time: HashMap<String, HashMap<String, f64>>,
-    ) -> Result<StopBusInfo, anyhow::Error> {
-        if let Some(stop) = self.stops.get(conf.stop_id) {
-            let buses = self
-                .stop_times
-                .get(conf.stop_id)
-                .cloned()
-                .unwrap_or_else(|| vec![]);
-
-            // Filter buses that don't come today.
-            let now = conf.after.time();
-            let today = conf.after.date();
-            let day = today.weekday();
-            let mut buses: Vec<_> = buses
-                .iter()
-                .filter_map(|bus| {
-                    let trip = self.trips.get(&bus.trip_id).expect("Trip id not found");
-                    let service = self
-                        .calendar
-                        .get(&trip.service_id)
-                        .expect("Service id not found");
-
-                    // Filter routes.
-                    if let Some(route) = conf.route {
-                        // If the short name is a number, then we don't want to mismatch because of
-                        // a leading 0... e.g. route 08 and route 8 should match.
-                        if trip.route_short_name.trim_start_matches("0")
-                            != route.trim_start_matches("0")
-                        {
-                            return None;
-                        }
-                    }
-
-                    // Check that today is in the range and on the right day of the week and not
-                    // during an exception.
-                    //
-                    // Moreover, filter out buses that already came.
-                    if service.start_date > today {
-                        None
-                    } else if service.end_date < today {
-                        None
-                    } else if !service.days.contains(Days::from_weekday(day)) {
-                        None
-                    } else if service.exceptions.iter().any(|ex| {
-                        ex.date == today
-                            && service.service_id == ex.service_id
-                            && ex.exception_type == ExceptionType::Removed
-                    }) {
-                        None
-                    } else if bus.departure_time < now {
-                        None
-                    } else {
-                        // Check for real-time delays.
-                        let delay = real_time
-                            .get(conf.stop_id)
-                            .iter()
-                            .flat_map(|stop| stop.get(&bus.trip_id).cloned())
-                            .next();
-
-                        Some((
-                            trip.route_short_name.clone(),
-                            trip.trip_headsign.clone(),
-                            bus.departure_time,
-                            delay,
-                        ))
-                    }
-                })
-                .collect();
-
-            buses.sort_by_key(|(_, _, time, delay)| {
-                time.overflowing_add_signed(chrono::Duration::seconds(delay.unwrap_or(0.0) as i64))
-            });
-
-            if let Some(len) = conf.how_many {
-                buses.truncate(len);
-            }
-
-            Ok(StopBusInfo {
-                stop_name: stop.stop_name.clone(),
-                buses,
-            })
-        } else {
-            bail!("No such bus stop")
-        }
-    }
-
-    pub fn search(&self, string: Vec<&str>) -> Vec<(String, String)> {
-        let strings: Vec<_> = string.iter().map(|s| s.to_lowercase()).collect();
-
-        let mut stops: Vec<(String, String)> = self
-            .stops
-            .values()
-            .filter_map(|stop| {
-                if strings
-                    .iter()
-                    .all(|string| stop.stop_name.to_lowercase().contains(string))
-                {
-                    Some((stop.stop_id.clone(), stop.stop_name.clone()))
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        stops.sort();
-
-        stops
-    }
-}
-
-macro_rules! warn_and_skip {
-    ($json:ident, $key:literal) => {{
-        if $json.has_key($key) {
-            $json.remove($key)
-        } else {
-            println!("Key {} not found in {}", $key, stringify!($json));
-            continue;
-        }
-    }};
-}
-
-// Hack your way through the real time data and produce by-stop-by-trip delay info.
-//
-// {stop_id: {trip_id: delay}}
-fn parse_real_time_data(
-    mut real_time_json: json::JsonValue,
-) -> Result<HashMap<String, HashMap<String, f64>>, anyhow::Error> {
-    assert!(real_time_json.has_key("entity"));
-
-    let mut by_stop_id_by_trip_id: HashMap<String, HashMap<String, f64>> = HashMap::new();
-
-    let mut entity = real_time_json.remove("entity");
-    for update in entity.members_mut() {
-        let mut trip_update = warn_and_skip!(update, "trip_update");
-        let mut trip = warn_and_skip!(trip_update, "trip");
-        let mut stop_time_update = warn_and_skip!(trip_update, "stop_time_update");
-        let trip_id = warn_and_skip!(trip, "trip_id")
-            .as_str()
-            .expect("expected str")
-            .to_owned();
-        let rolling_delay = 0.0;
-        for stop_time in stop_time_update.members_mut() {
-            let stop_id = warn_and_skip!(stop_time, "stop_id")
-                .as_str()
-                .expect("expected str")
-                .to_owned();
-            let mut departure = warn_and_skip!(stop_time, "departure");
-            let delay = if departure.has_key("delay") {
-                departure.remove("delay").as_f64().expect("expected usize")
-            } else {
-                rolling_delay
-            };
-
-            if delay > 0.0 {
-                by_stop_id_by_trip_id
-                    .entry(stop_id)
-                    .or_default()
-                    .insert(trip_id.clone(), delay);
-            }
-        }
-    }
-    Ok(by_stop_id_by_trip_id)
-}
-
 fn print_delay(delay: chrono::Duration) -> String {
+    let (sign, delay) = if delay < chrono::Duration::zero() {
+        ("-", -delay)
+    } else {
+        ("+", delay)
+    };
+
     if delay >= chrono::Duration::minutes(1) {
         let minutes = delay.num_minutes();
         let seconds = delay.num_seconds() - minutes * 60;
         if seconds > 0 {
-            format!("{}m {}s", minutes, seconds)
+            format!("{} {}m {}s", sign, minutes, seconds)
         } else {
-            format!("{}m", minutes)
+            format!("{} {}m", sign, minutes)
         }
     } else {
         let seconds = delay.num_seconds();
-        format!("{}s", seconds)
+        format!("{} {}s", sign, seconds)
     }
 }
 
-fn do_update(data_dir: &str) {
-    // remove old.
-    if path::PathBuf::from(data_dir).is_dir() {
-        fs::remove_dir_all(data_dir).expect("Unable to remove old data.");
+/// How long until a bus comes, the way the signs at stations show it.
+fn print_countdown(until: chrono::Duration) -> String {
+    if until < -chrono::Duration::minutes(1) {
+        "gone".into()
+    } else if until < chrono::Duration::minutes(1) {
+        "due".into()
+    } else {
+        format!("in {} min", until.num_minutes())
     }
+}
 
-    // create empty new.
-    fs::create_dir_all(data_dir).expect("Unable to create new data directory.");
-
-    // download data.
-    const DATA_ZIP: &str = "data.zip";
-    let mut zipped = fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(path::PathBuf::from(data_dir).join(DATA_ZIP))
-        .expect("Unable to open data zip file for writing.");
-    reqwest::blocking::get(GTFS_DATA_URL)
-        .expect("Unable to download schedule data.")
-        .copy_to(&mut zipped)
-        .expect("Unable to write received data to file.");
-
-    // unzip the file.
-    let status = std::process::Command::new("unzip")
-        .current_dir(data_dir)
-        .arg(DATA_ZIP)
-        .status()
-        .expect("Unable to unzip downloaded schedule data.");
-    if !status.success() {
-        panic!("Unzip failed with status: {}", status);
+/// The status column: whether the bus runs as scheduled and, if so, how late it is.
+fn print_status(status: BusStatus, delay: Option<f64>) -> String {
+    match status {
+        BusStatus::Cancelled => "CANCELLED".into(),
+        BusStatus::Skipped => "SKIPPED".into(),
+        BusStatus::Added => "ADDED".into(),
+        BusStatus::Scheduled => {
+            if let Some(delay) = delay.filter(|delay| *delay != 0.0) {
+                print_delay(chrono::Duration::seconds(delay as i64))
+            } else {
+                "".into()
+            }
+        }
     }
 }
 
+/// A time in the agency's zone, moved to the zone given with `--tz` for display.
+fn shown(data: &Data, tz: Option<Tz>, time: NaiveDateTime) -> NaiveDateTime {
+    tz.map_or(time, |tz| data.in_timezone(time, tz))
+}
+
 fn main() -> Result<(), anyhow::Error> {
     let matches = clap_app! { bus =>
         (about: "Info about scheduled buses.")
+        (@arg AGENCY: +takes_value +global --agency
+         "Use the named agency profile from the config file.")
+        (@arg FORMAT: +takes_value +global --format possible_values(OutputFormat::NAMES)
+         "Print results for people (human, the default) or for scripts (json, csv, tsv).")
+        (@arg TZ: +takes_value +global --tz {is_timezone}
+         "Show times in the given time zone (e.g. America/New_York, UTC or local) instead of \
+         the agency's. Times given as options, and with --format, stay in the agency's zone.")
         (@subcommand stop =>
             (about: "lists the next scheduled buses at the given stop")
-            (@arg STOP: +required "The stop ID")
-            (@arg WHEN: +takes_value --after -a {is_time}
-             "List stops at or after the given time (local to Madison) today \
-             (HH:MM, 24-hour clock).")
+            (@arg STOP: +required
+             "The stop ID. Buses at the other stops of the same station are listed too.")
+            (@arg WHEN: +takes_value --after -a {is_when}
+             "List buses at or after the given time in the agency's time zone, e.g. 07:30, 8am, \
+             tomorrow 8am, sat or +20m.")
+            (@arg DATE: +takes_value --date -d {is_date}
+             "List buses on the given date (YYYY-MM-DD) instead of today.")
             (@arg N: +takes_value --next -n {is_usize}
              "List the next N buses.")
             (@arg ROUTE: +takes_value --route -r
              "List only busses taking route ROUTE.")
+            (@arg ARRIVALS: --arrivals
+             "List buses arriving rather than leaving, including ones ending their trip here.")
+            (@arg COUNTDOWN: --countdown -c
+             "Show when each bus is predicted to come, and how long until then, instead of its \
+             scheduled time and delay.")
+            (@arg SHOW_TRIP_IDS: --("show-trip-ids")
+             "Show each bus's trip ID, for use with the trip command.")
+            (@arg NEARBY: --nearby
+             "Also list buses at the stops within walking distance.")
+            (@arg WHEELCHAIR: --wheelchair
+             "List only wheelchair accessible buses, at stops where wheelchairs can board.")
+            (@arg BIKES: --bikes
+             "List only buses that take bikes.")
+        )
+        (@subcommand watch =>
+            (about: "Keeps showing the next buses at the given stops, updating as the real-time \
+             data changes")
+            (@arg STOP: +required ... "The stop IDs")
+            (@arg N: +takes_value --next -n {is_usize}
+             "Show the next N buses at each stop.")
+            (@arg INTERVAL: +takes_value --interval -i {is_usize}
+             "Fetch the real-time data every INTERVAL seconds instead of every 30.")
+        )
+        (@subcommand trip =>
+            (about: "Follows one trip along its route, showing where the bus is")
+            (@arg TRIP: +required "The trip ID, e.g. from `stop --show-trip-ids`")
+        )
+        (@subcommand plan =>
+            (about: "Plans a journey from one stop to another, with transfers")
+            (@arg FROM: +required "The stop ID to start from")
+            (@arg TO: +required "The stop ID to go to")
+            (@arg DEPART: +takes_value --depart {is_when}
+             "Leave at or after the given time instead of now, e.g. 07:30, tomorrow 8am or +20m.")
+            (@arg ARRIVE_BY: +takes_value --("arrive-by") {is_when} conflicts_with[DEPART]
+             "Arrive at or before the given time, e.g. 17:30 or sat 5pm.")
         )
         (@subcommand search =>
-            (about: "Searches for all bus stops that contain the given string")
+            (about: "Searches for bus stops by name, street or stop code, best matches first")
             (@arg STR: +required ... "The string(s) to search for")
         )
+        (@subcommand near =>
+            (about: "Lists the stops near a place, nearest first")
+            (@setting AllowNegativeNumbers)
+            (@arg LAT: +required {is_latitude} "The latitude, e.g. 43.0731")
+            (@arg LON: +required {is_longitude} "The longitude, e.g. -89.4012")
+            (@arg RADIUS: +takes_value --radius -r {is_distance}
+             "Look this far away (e.g. 400m, 1.5km, 0.25mi) instead of 400m.")
+            (@arg DEPARTURES: --departures
+             "Show the next bus at each stop.")
+        )
+        (@subcommand routes =>
+            (about: "Lists the routes running on a day, with their first and last trips")
+            (@arg DATE: +takes_value --date -d {is_date}
+             "List routes running on the given date (YYYY-MM-DD) instead of today.")
+        )
+        (@subcommand timetable =>
+            (about: "Prints the timetable of a route: its timepoint stops and the day's trips")
+            (@arg ROUTE: +required "The route, e.g. 80")
+            (@arg DIRECTION: +takes_value --direction
+             "Show only the given direction (e.g. 0 or 1) instead of all of them.")
+            (@arg DATE: +takes_value --date -d {is_date}
+             "Show the trips running on the given date (YYYY-MM-DD) instead of today.")
+        )
+        (@subcommand tui =>
+            (about: "Browses stops, their buses, trips and timetables in a full-screen interface")
+        )
         (@subcommand update =>
             (about: "Attempts to update GTFS schedule data.")
+            (@arg ROLLBACK: --rollback
+             "Restore the schedule data from before the last update.")
         )
 
     }
     .setting(clap::AppSettings::SubcommandRequiredElseHelp)
     .get_matches();
 
-    // Read the static bus schedule data.
-    let data_dir = std::env::var("BUS_DATA").expect(
-        "BUS_DATA environment variable needs to be set to the \
-         directory containing GTFS data. Optionally, set BUS_DATA \
-         and run the `update` command.",
-    );
+    // Find the agency's feeds and data.
+    let agency_name = matches
+        .subcommand()
+        .1
+        .and_then(|sub_m| sub_m.value_of("AGENCY"))
+        .or_else(|| matches.value_of("AGENCY"));
+    let config = Config::load()?;
+    let agency = config.agency(agency_name)?;
+    let format: OutputFormat = matches
+        .subcommand()
+        .1
+        .and_then(|sub_m| sub_m.value_of("FORMAT"))
+        .or_else(|| matches.value_of("FORMAT"))
+        .unwrap_or("human")
+        .parse()?;
+    let data_dir = &agency.data;
+    let tz = matches
+        .subcommand()
+        .1
+        .and_then(|sub_m| sub_m.value_of("TZ"))
+        .or_else(|| matches.value_of("TZ"))
+        .map(|tz| when::parse_timezone(tz).unwrap());
 
     // Do computations and print stuff.
     match matches.subcommand() {
         ("stop", Some(sub_m)) => {
             let stop = sub_m.value_of("STOP").unwrap();
 
-            let mut filter = FilterConfig::new(stop);
+            let mut data = Data::read(data_dir)?;
+            data.walking = config.walking();
 
-            if let Some(after) = sub_m.value_of("WHEN") {
-                filter = filter.after(
-                    Local::now().date_naive().and_time(
-                        NaiveTime::parse_from_str(after, "%H:%M")
-                            .unwrap_or_else(|_| NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
-                    ),
-                );
-            }
+            let mut filter = FilterConfig::new(stop).station();
+
+            // Times are relative to now, or to the start of the day asked about.
+            let now = data.now();
+            let base = match sub_m.value_of("DATE") {
+                Some(date) => {
+                    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                    if date == now.date() {
+                        now
+                    } else {
+                        date.and_hms_opt(0, 0, 0).unwrap()
+                    }
+                }
+                None => now,
+            };
+            let after = match sub_m.value_of("WHEN") {
+                Some(after) => parse_when(after, base)?,
+                None => base,
+            };
+            filter = filter.after(after);
 
             filter = filter.how_many(
                 sub_m
@@ -631,62 +262,427 @@ fn main() -> Result<(), anyhow::Error> {
                 filter = filter.route(route);
             }
 
+            if sub_m.is_present("ARRIVALS") {
+                filter = filter.arrivals();
+            }
+
+            if sub_m.is_present("NEARBY") {
+                filter = filter.nearby();
+            }
+
+            if sub_m.is_present("WHEELCHAIR") {
+                filter = filter.wheelchair();
+            }
+
+            if sub_m.is_present("BIKES") {
+                filter = filter.bikes();
+            }
+
             // Read the real time trip update.
-            let real_time_json_raw = reqwest::blocking::get(TRIP_UPDATE_URL)?.text();
-            let real_time_info = if let Ok(real_time_json_raw) = real_time_json_raw {
-                if let Ok(real_time_json) = json::parse(&real_time_json_raw) {
-                    if let Ok(real_time_json) = parse_real_time_data(real_time_json) {
-                        real_time_json
-                    } else {
-                        println!("WARNING: Unable to parse real-time data.");
-                        Default::default()
-                    }
-                } else {
-                    println!("WARNING: Unable to parse real-time data json.");
-                    Default::default()
-                }
+            let real_time_info = fetch_real_time_or_warn(agency.trip_update_url.as_deref());
+
+            let bus_info = data.stop_sched(filter, &real_time_info)?;
+
+            if format != OutputFormat::Human {
+                return format.write(&bus_info.records());
+            }
+
+            let show_trip_ids = sub_m.is_present("SHOW_TRIP_IDS");
+            let countdown = sub_m.is_present("COUNTDOWN");
+            // Label each bus with its stop when listing several.
+            let platform_width = if bus_info.stop_ids.len() > 1 {
+                bus_info
+                    .buses
+                    .iter()
+                    .map(|bus| bus.platform.chars().count() + 1)
+                    .max()
+                    .unwrap_or(0)
             } else {
-                println!("WARNING: Unable to fetch real-time data json.");
-                Default::default()
+                0
             };
 
-            let data = Data::read(&data_dir)?;
-            let bus_info = data.stop_sched(filter, real_time_info)?;
-
-            println!("{}", bus_info.stop_name);
-            for (bus, headsign, time, delay) in bus_info.buses.iter() {
-                println!(
-                    "{} {:10} {}  {}",
-                    time.format("%l:%M %p"),
-                    if let Some(delay) = delay {
-                        format!(
-                            "+ {}",
-                            print_delay(chrono::Duration::seconds(*delay as i64))
-                        )
+            if after.date() == now.date() {
+                println!("{}", bus_info.stop_name);
+            } else {
+                println!("{} ({})", bus_info.stop_name, after.format("%a %b %-d"));
+            }
+            if bus_info.wheelchair_boarding == Accessibility::No {
+                println!("[This stop is not wheelchair accessible]");
+            }
+            for bus in bus_info.buses.iter() {
+                let (time, status) = match bus.status {
+                    BusStatus::Scheduled | BusStatus::Added if countdown => (
+                        bus.predicted_departure(),
+                        print_countdown(bus.predicted_departure() - now),
+                    ),
+                    _ if countdown => (bus.predicted_departure(), print_status(bus.status, None)),
+                    _ => (bus.departure, print_status(bus.status, bus.delay)),
+                };
+                print!(
+                    "{} {:10} {:platform_width$}{}  {}",
+                    shown(&data, tz, time).format("%l:%M %p"),
+                    status,
+                    if platform_width > 0 {
+                        &bus.platform
                     } else {
-                        "".into()
+                        ""
                     },
-                    bus,
-                    headsign,
-                )
+                    bus.route,
+                    bus.headsign,
+                    platform_width = platform_width,
+                );
+                if bus.wheelchair == Accessibility::Yes {
+                    print!(" ♿");
+                }
+                if bus.bikes == Accessibility::Yes {
+                    print!(" 🚲");
+                }
+                if bus.last_stop {
+                    print!(" (last stop)");
+                }
+                if show_trip_ids {
+                    print!("  [{}]", bus.trip_id);
+                }
+                println!();
             }
             if bus_info.buses.is_empty() {
-                println!("[No more buses today]");
+                println!("[No buses in the next 24 hours]");
+            }
+        }
+
+        ("watch", Some(sub_m)) => {
+            if format != OutputFormat::Human {
+                bail!("watch only prints for people; use `stop` with --format instead");
+            }
+
+            let stops: Vec<_> = sub_m.values_of("STOP").unwrap().collect();
+            let how_many = sub_m
+                .value_of("N")
+                .map(|n| n.parse::<usize>().unwrap())
+                .unwrap_or(DEFAULT_N / 2);
+            let interval = sub_m
+                .value_of("INTERVAL")
+                .map(|interval| interval.parse::<u64>().unwrap())
+                .unwrap_or(DEFAULT_WATCH_INTERVAL)
+                .max(1);
+
+            let data = Data::read(data_dir)?;
+            watch(
+                &data,
+                &stops,
+                how_many,
+                Duration::from_secs(interval),
+                agency.trip_update_url.as_deref(),
+                tz,
+            )?;
+        }
+
+        ("trip", Some(sub_m)) => {
+            let trip_id = sub_m.value_of("TRIP").unwrap();
+
+            let real_time_info = fetch_real_time_or_warn(agency.trip_update_url.as_deref());
+
+            let data = Data::read(data_dir)?;
+            let trip = data.trip_sched(trip_id, data.now(), &real_time_info)?;
+
+            if format != OutputFormat::Human {
+                return format.write(&trip.records());
+            }
+
+            println!(
+                "Route {} to {} ({})",
+                trip.route, trip.headsign, trip.service_day
+            );
+            for stop in trip.stops.iter() {
+                let time = |time: Option<NaiveDateTime>| {
+                    time.map_or_else(String::new, |time| {
+                        shown(&data, tz, time).format("%l:%M %p").to_string()
+                    })
+                };
+                // Only show the prediction when it differs from the schedule.
+                let predicted = if stop.delay.is_some_and(|delay| delay != 0.0) {
+                    time(stop.predicted_departure())
+                } else {
+                    String::new()
+                };
+
+                println!(
+                    "{} {:8} {:8} {:10} {}",
+                    if stop.passed { "✓" } else { " " },
+                    time(stop.departure),
+                    predicted,
+                    print_status(stop.status, stop.delay),
+                    stop.stop_name,
+                );
+            }
+            if trip.stops.is_empty() {
+                println!("[Trip {} has no stops]", trip.trip_id);
+            }
+        }
+
+        ("plan", Some(sub_m)) => {
+            let from = sub_m.value_of("FROM").unwrap();
+            let to = sub_m.value_of("TO").unwrap();
+            let mut data = Data::read(data_dir)?;
+            data.walking = config.walking();
+
+            let now = data.now();
+            let when = match (sub_m.value_of("DEPART"), sub_m.value_of("ARRIVE_BY")) {
+                (_, Some(arrive_by)) => PlanTime::ArriveBy(parse_when(arrive_by, now)?),
+                (Some(depart), None) => PlanTime::Depart(parse_when(depart, now)?),
+                (None, None) => PlanTime::Depart(now),
+            };
+
+            let journeys = data.plan(from, to, when)?;
+
+            if format != OutputFormat::Human {
+                let records: Vec<_> = journeys
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, journey)| journey.records(i + 1))
+                    .collect();
+                return format.write(&records);
+            }
+
+            // Late at night, the best journey may be on the next day.
+            let day = match when {
+                PlanTime::Depart(time) | PlanTime::ArriveBy(time) => shown(&data, tz, time).date(),
+            };
+            let print_time = |time: NaiveDateTime| {
+                let time = shown(&data, tz, time);
+                if time.date() == day {
+                    time.format("%l:%M %p").to_string()
+                } else {
+                    time.format("%a %l:%M %p").to_string()
+                }
+            };
+            for journey in journeys.iter() {
+                println!(
+                    "{} - {} ({} min, {} transfer{})",
+                    print_time(journey.departure()),
+                    print_time(journey.arrival()),
+                    (journey.arrival() - journey.departure()).num_minutes(),
+                    journey.transfers(),
+                    if journey.transfers() == 1 { "" } else { "s" },
+                );
+                for leg in journey.legs.iter() {
+                    if leg.walking {
+                        println!(
+                            "  {}  walk {} min to {}",
+                            print_time(leg.departure),
+                            (leg.arrival - leg.departure).num_minutes().max(1),
+                            leg.to_stop_name,
+                        );
+                        continue;
+                    }
+                    println!(
+                        "  {}  {} {} from {}",
+                        print_time(leg.departure),
+                        leg.route,
+                        leg.headsign,
+                        leg.from_stop_name,
+                    );
+                    println!(
+                        "  {}  get off at {}",
+                        print_time(leg.arrival),
+                        leg.to_stop_name,
+                    );
+                }
+                println!();
+            }
+            if journeys.is_empty() {
+                println!("[No journeys found]");
             }
         }
 
         ("search", Some(sub_m)) => {
             let strings = sub_m.values_of("STR").unwrap().collect();
-            let data = Data::read(&data_dir)?;
+            let data = Data::read(data_dir)?;
             let stops = data.search(strings);
 
+            if format != OutputFormat::Human {
+                let records: Vec<_> = stops
+                    .iter()
+                    .map(|(stop_id, stop_name)| StopRecord { stop_id, stop_name })
+                    .collect();
+                return format.write(&records);
+            }
+
             for (id, stop) in stops {
                 println!("{} {}", id, stop);
             }
         }
 
-        ("update", Some(_)) => {
-            do_update(&data_dir);
+        ("near", Some(sub_m)) => {
+            let place = LatLon::new(
+                sub_m.value_of("LAT").unwrap().parse().unwrap(),
+                sub_m.value_of("LON").unwrap().parse().unwrap(),
+            );
+            let radius = sub_m
+                .value_of("RADIUS")
+                .map(|radius| geo::parse_distance(radius).unwrap())
+                .unwrap_or(DEFAULT_RADIUS);
+
+            let real_time_info = if sub_m.is_present("DEPARTURES") {
+                fetch_real_time_or_warn(agency.trip_update_url.as_deref())
+            } else {
+                Default::default()
+            };
+
+            let data = Data::read(data_dir)?;
+            let stops = data.near(place, radius);
+            let next_buses = stops
+                .iter()
+                .map(|nearby| {
+                    if !sub_m.is_present("DEPARTURES") {
+                        return Ok(None);
+                    }
+                    let filter = FilterConfig::new(&nearby.stop.stop_id).how_many(1);
+                    Ok(data.stop_sched(filter, &real_time_info)?.buses.pop())
+                })
+                .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+            if format != OutputFormat::Human {
+                let records: Vec<_> = stops
+                    .iter()
+                    .zip(next_buses.iter())
+                    .map(|(nearby, next)| nearby.record(next.as_ref()))
+                    .collect();
+                return format.write(&records);
+            }
+
+            for (nearby, next) in stops.iter().zip(next_buses.iter()) {
+                let distance = if nearby.distance < 1000.0 {
+                    format!("{:.0} m", nearby.distance)
+                } else {
+                    format!("{:.1} km", nearby.distance / 1000.0)
+                };
+                print!(
+                    "{:>8} {:2} {:>6}  {} {}",
+                    distance,
+                    // Standing at the stop, it has no direction.
+                    if nearby.distance < 1.0 {
+                        ""
+                    } else {
+                        geo::compass_point(nearby.bearing)
+                    },
+                    nearby.stop.stop_id,
+                    nearby.stop.stop_name,
+                    nearby.stop.cardinal_direction,
+                );
+                if let Some(bus) = next {
+                    print!(
+                        "  (next: {} {} {} {})",
+                        shown(&data, tz, bus.departure)
+                            .format("%l:%M %p")
+                            .to_string()
+                            .trim(),
+                        print_status(bus.status, bus.delay),
+                        bus.route,
+                        bus.headsign,
+                    );
+                }
+                println!();
+            }
+            if stops.is_empty() {
+                println!("[No stops within {:.0} m]", radius);
+            }
+        }
+
+        ("routes", Some(sub_m)) => {
+            let data = Data::read(data_dir)?;
+            let date = sub_m
+                .value_of("DATE")
+                .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+                .unwrap_or_else(|| data.now().date());
+            let routes = data.routes_on(date);
+
+            if format != OutputFormat::Human {
+                let records: Vec<_> = routes.iter().map(|route| route.record()).collect();
+                return format.write(&records);
+            }
+
+            for route in routes.iter() {
+                println!(
+                    "{:>4} {} - {}  {:40} {}",
+                    route.route_short_name,
+                    shown(&data, tz, route.first_departure).format("%l:%M %p"),
+                    shown(&data, tz, route.last_arrival).format("%l:%M %p"),
+                    route.route.map_or("", |route| &route.route_long_name),
+                    route.service_names.join(", "),
+                );
+            }
+            if routes.is_empty() {
+                println!("[No routes running on {}]", date);
+            }
+        }
+
+        ("timetable", Some(sub_m)) => {
+            let route = sub_m.value_of("ROUTE").unwrap();
+            let data = Data::read(data_dir)?;
+            let date = sub_m
+                .value_of("DATE")
+                .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+                .unwrap_or_else(|| data.now().date());
+            let timetables = data.timetable(route, sub_m.value_of("DIRECTION"), date);
+
+            if format != OutputFormat::Human {
+                let records: Vec<_> = timetables.iter().flat_map(|t| t.records()).collect();
+                return format.write(&records);
+            }
+
+            for timetable in timetables.iter() {
+                println!(
+                    "Route {} {} (direction {})",
+                    timetable.route_short_name, timetable.direction_name, timetable.direction_id
+                );
+                for (i, (_, stop_name)) in timetable.stops.iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, stop_name);
+                }
+                println!();
+
+                let header: Vec<_> = (1..=timetable.stops.len())
+                    .map(|i| format!("{:>8}", i))
+                    .collect();
+                println!("{}  Headsign", header.join(" "));
+                for trip in timetable.trips.iter() {
+                    let times: Vec<_> = trip
+                        .times
+                        .iter()
+                        .map(|time| match time {
+                            Some(time) => format!(
+                                "{:>8}",
+                                shown(&data, tz, *time).format("%l:%M %p").to_string()
+                            ),
+                            None => format!("{:>8}", "-"),
+                        })
+                        .collect();
+                    println!("{}  {}", times.join(" "), trip.headsign);
+                }
+                println!();
+            }
+            if timetables.is_empty() {
+                println!("[Route {} does not run on {}]", route, date);
+            }
+        }
+
+        ("tui", Some(_)) => {
+            if format != OutputFormat::Human {
+                bail!("tui only shows things for people; use the other commands with --format");
+            }
+
+            let agency_name = config.agency_name(agency_name)?.to_owned();
+            let mut data = Data::read(data_dir)?;
+            data.walking = config.walking();
+            tui::App::new(&data, agency_name, agency.trip_update_url.clone(), tz)?.run()?;
+        }
+
+        ("update", Some(sub_m)) => {
+            if sub_m.is_present("ROLLBACK") {
+                update::rollback(data_dir)?;
+            } else {
+                update::update(data_dir, &agency.gtfs_url)?;
+            }
         }
 
         _ => unreachable!(),
@@ -695,6 +691,140 @@ fn main() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Keep redrawing the next buses at the given stops, fetching the real-time data every
+/// `interval`. Runs until interrupted.
+fn watch(
+    data: &Data,
+    stops: &[&str],
+    how_many: usize,
+    interval: Duration,
+    trip_update_url: Option<&str>,
+    tz: Option<Tz>,
+) -> Result<(), anyhow::Error> {
+    // Highlight only on a terminal, so that the output can still be piped somewhere.
+    let color = std::io::stdout().is_terminal();
+    let highlight = |text: String| {
+        if color {
+            format!("\x1b[1;33m{}\x1b[0m", text)
+        } else {
+            text
+        }
+    };
+
+    let mut real_time = RealTimeInfo::default();
+    let mut fetched: Option<DateTime<Utc>> = None;
+    let mut error: Option<anyhow::Error> = None;
+    // The delay of each bus at each stop, by (stop_id, trip_id), to spot the ones that change.
+    let mut delays: HashMap<(String, String), Option<f64>> = HashMap::new();
+    let mut changed: HashSet<(String, String)> = HashSet::new();
+    let mut next_fetch = Instant::now();
+
+    loop {
+        if Instant::now() >= next_fetch {
+            next_fetch = Instant::now() + interval;
+            if let Some(url) = trip_update_url {
+                match realtime::fetch_real_time_data(url) {
+                    Ok(update) => {
+                        real_time = update;
+                        fetched = Some(Utc::now());
+                        error = None;
+                    }
+                    // Keep showing the last data we got.
+                    Err(e) => error = Some(e),
+                }
+            }
+
+            let mut new_delays = HashMap::new();
+            for stop in stops {
+                let filter = FilterConfig::new(stop).station().how_many(how_many);
+                for bus in data.stop_sched(filter, &real_time)?.buses {
+                    new_delays.insert((bus.stop_id, bus.trip_id), bus.delay);
+                }
+            }
+            changed = new_delays
+                .iter()
+                .filter(|(bus, delay)| delays.get(*bus).is_some_and(|old| old != *delay))
+                .map(|(bus, _)| bus.clone())
+                .collect();
+            delays = new_delays;
+        }
+
+        let now = data.now();
+        let mut screen = String::new();
+        writeln!(
+            screen,
+            "Next buses at {}",
+            shown(data, tz, now)
+                .format("%l:%M:%S %p")
+                .to_string()
+                .trim()
+        )?;
+        for stop in stops {
+            let filter = FilterConfig::new(stop).station().how_many(how_many);
+            let bus_info = data.stop_sched(filter, &real_time)?;
+
+            writeln!(screen)?;
+            writeln!(screen, "{}", bus_info.stop_name)?;
+            let platforms = bus_info.stop_ids.len() > 1;
+            for bus in bus_info.buses.iter() {
+                let countdown = match bus.status {
+                    BusStatus::Scheduled | BusStatus::Added => {
+                        print_countdown(bus.predicted_departure() - now)
+                    }
+                    _ => String::new(),
+                };
+                let mut status = format!("{:10}", print_status(bus.status, bus.delay));
+                if changed.contains(&(bus.stop_id.clone(), bus.trip_id.clone())) {
+                    status = highlight(status);
+                }
+                writeln!(
+                    screen,
+                    "{} {:10} {} {}{}  {}",
+                    shown(data, tz, bus.predicted_departure()).format("%l:%M %p"),
+                    countdown,
+                    status,
+                    if platforms {
+                        format!("{} ", bus.platform)
+                    } else {
+                        String::new()
+                    },
+                    bus.route,
+                    bus.headsign,
+                )?;
+            }
+            if bus_info.buses.is_empty() {
+                writeln!(screen, "[No buses in the next 24 hours]")?;
+            }
+        }
+
+        writeln!(screen)?;
+        // Feeds say when they were made; otherwise go by when we got it.
+        match (trip_update_url, real_time.timestamp.or(fetched)) {
+            (None, _) => writeln!(screen, "[No real-time data for this agency]")?,
+            (Some(_), None) => writeln!(screen, "[No real-time data yet]")?,
+            (Some(_), Some(made)) => writeln!(
+                screen,
+                "[Real-time data is {} s old]",
+                (Utc::now() - made).num_seconds().max(0)
+            )?,
+        }
+        if let Some(e) = &error {
+            writeln!(screen, "WARNING: {:#}", e)?;
+        }
+
+        // Clear the terminal and draw from the top.
+        print!("\x1b[2J\x1b[H{}", screen);
+        std::io::stdout().flush()?;
+
+        let redraw = Duration::from_secs(WATCH_REDRAW);
+        std::thread::sleep(
+            next_fetch
+                .saturating_duration_since(Instant::now())
+                .min(redraw),
+        );
+    }
+}
+
 fn is_usize(s: String) -> Result<(), String> {
     s.as_str()
         .parse::<usize>()
@@ -702,7 +832,50 @@ fn is_usize(s: String) -> Result<(), String> {
         .map_err(|e| format!("{:?}", e))
 }
 
-fn is_time(s: String) -> Result<(), String> {
-    NaiveTime::parse_from_str(&s, "%H:%M").map_err(|e| format!("Could not parse time: {}", e))?;
+/// Parse a time given as an option, relative to `now` in the agency's zone.
+fn parse_when(when: &str, now: NaiveDateTime) -> Result<NaiveDateTime, anyhow::Error> {
+    match when::parse(when, now) {
+        Some(time) => Ok(time),
+        None => bail!("Could not parse time: {}", when),
+    }
+}
+
+// The agency's zone isn't known until the data is read, so this only checks the form of the
+// time. `parse_when` reads it for real.
+fn is_when(s: String) -> Result<(), String> {
+    when::parse(&s, Utc::now().naive_utc())
+        .map(|_| ())
+        .ok_or_else(|| format!("Could not parse time: {}", s))
+}
+
+fn is_timezone(s: String) -> Result<(), String> {
+    when::parse_timezone(&s)
+        .map(|_| ())
+        .ok_or_else(|| format!("Unknown time zone: {}", s))
+}
+
+fn is_latitude(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(degrees) if (-90.0..=90.0).contains(&degrees) => Ok(()),
+        _ => Err(format!("Could not parse latitude: {}", s)),
+    }
+}
+
+fn is_longitude(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(degrees) if (-180.0..=180.0).contains(&degrees) => Ok(()),
+        _ => Err(format!("Could not parse longitude: {}", s)),
+    }
+}
+
+fn is_distance(s: String) -> Result<(), String> {
+    geo::parse_distance(&s)
+        .map(|_| ())
+        .ok_or_else(|| format!("Could not parse distance: {}", s))
+}
+
+fn is_date(s: String) -> Result<(), String> {
+    NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+        .map_err(|e| format!("Could not parse date: {}", e))?;
     Ok(())
 }
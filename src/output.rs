@@ -0,0 +1,182 @@
+//! Machine-readable output for scripts and status bars.
+//!
+//! Each subcommand turns its results into flat records with stable field names, which are then
+//! written as a JSON array or as CSV/TSV with a header row.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use anyhow::bail;
+
+use serde::Serialize;
+
+/// How times are written in records: ISO 8601 without an offset, in the agency's time zone.
+pub const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// How to print results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// For people, laid out by each subcommand.
+    Human,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => bail!("Unknown output format: {}", s),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// The values accepted on the command line.
+    pub const NAMES: &'static [&'static str] = &["human", "json", "csv", "tsv"];
+
+    /// Write the records to stdout. Must not be called with `Human`, which each subcommand prints
+    /// its own way.
+    pub fn write<T: Serialize>(self, records: &[T]) -> Result<(), anyhow::Error> {
+        let stdout = io::stdout();
+        let delimiter = match self {
+            OutputFormat::Human => unreachable!("human output is printed by the subcommand"),
+            OutputFormat::Json => {
+                let mut out = stdout.lock();
+                serde_json::to_writer_pretty(&mut out, records)?;
+                writeln!(out)?;
+                return Ok(());
+            }
+            OutputFormat::Csv => b',',
+            OutputFormat::Tsv => b'\t',
+        };
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(stdout.lock());
+        for record in records {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A departure, as written by `--format`.
+#[derive(Serialize)]
+pub struct DepartureRecord<'a> {
+    pub stop_id: &'a str,
+    pub stop_name: &'a str,
+    /// Which of a station's stops the bus comes to, e.g. its platform.
+    pub platform: &'a str,
+    pub route: &'a str,
+    pub headsign: &'a str,
+    pub status: &'static str,
+    pub scheduled_time: String,
+    pub delay_seconds: Option<i64>,
+    pub predicted_time: String,
+    pub trip_id: &'a str,
+    /// Whether the trip ends at this stop.
+    pub last_stop: bool,
+    /// `yes`, `no` or `unknown`.
+    pub wheelchair_accessible: &'static str,
+    pub bikes_allowed: &'static str,
+}
+
+/// A stop found by `search`, as written by `--format`.
+#[derive(Serialize)]
+pub struct StopRecord<'a> {
+    pub stop_id: &'a str,
+    pub stop_name: &'a str,
+}
+
+/// A route running on some day, as written by `--format`.
+#[derive(Serialize)]
+pub struct RouteRecord<'a> {
+    pub route_id: &'a str,
+    pub route_short_name: &'a str,
+    pub route_long_name: &'a str,
+    pub route_type: &'static str,
+    pub route_color: Option<&'a str>,
+    pub route_text_color: Option<&'a str>,
+    pub first_departure: String,
+    pub last_arrival: String,
+    /// Separated by `;`.
+    pub service_names: String,
+}
+
+/// One trip stopping at one stop of a timetable, as written by `--format`.
+#[derive(Serialize)]
+pub struct TimetableRecord<'a> {
+    pub route_short_name: &'a str,
+    pub direction_id: &'a str,
+    pub trip_id: &'a str,
+    pub headsign: &'a str,
+    pub stop_id: &'a str,
+    pub stop_name: &'a str,
+    pub departure_time: String,
+}
+
+/// One stop of a trip, as written by `--format`.
+#[derive(Serialize)]
+pub struct TripStopRecord<'a> {
+    pub trip_id: &'a str,
+    pub route: &'a str,
+    pub headsign: &'a str,
+    pub stop_sequence: u32,
+    pub stop_id: &'a str,
+    pub stop_name: &'a str,
+    pub status: &'static str,
+    /// Empty if the stop has no scheduled time.
+    pub scheduled_time: String,
+    pub delay_seconds: Option<i64>,
+    pub predicted_time: String,
+    pub passed: bool,
+}
+
+/// One leg of a planned journey, as written by `--format`.
+#[derive(Serialize)]
+pub struct PlanLegRecord<'a> {
+    /// Which of the planned journeys this leg belongs to, from 1.
+    pub journey: usize,
+    pub transfers: usize,
+    /// The leg's place in the journey, from 1.
+    pub leg: usize,
+    /// `bus` or `walk`. Walks leave `route`, `headsign` and `trip_id` empty.
+    pub mode: &'static str,
+    pub route: &'a str,
+    pub headsign: &'a str,
+    pub trip_id: &'a str,
+    pub from_stop_id: &'a str,
+    pub from_stop_name: &'a str,
+    pub departure_time: String,
+    pub to_stop_id: &'a str,
+    pub to_stop_name: &'a str,
+    pub arrival_time: String,
+}
+
+/// A stop near some place, as written by `--format`.
+#[derive(Serialize)]
+pub struct NearbyStopRecord<'a> {
+    pub stop_id: &'a str,
+    pub stop_code: &'a str,
+    pub stop_name: &'a str,
+    pub distance_meters: u64,
+    /// From the place to the stop, clockwise from north.
+    pub bearing_degrees: u64,
+    /// The bearing as a compass point, e.g. `NE`.
+    pub compass: &'static str,
+    /// The direction buses travel at the stop, as the feed gives it.
+    pub cardinal_direction: &'a str,
+    /// The next bus, if asked for.
+    pub next_route: Option<&'a str>,
+    pub next_headsign: Option<&'a str>,
+    pub next_predicted_time: Option<String>,
+}
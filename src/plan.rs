@@ -0,0 +1,594 @@
+//! Planning a journey between two stops, with transfers.
+//!
+//! This is RAPTOR (Delling, Pajor and Werneck, "Round-Based Public Transit Routing"). Round `k`
+//! finds the earliest arrival at every stop using at most `k` trips, so the rounds together give
+//! the fastest journey for each number of transfers. Planning to arrive by a deadline runs the
+//! same search backwards in time.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::gtfs::{ServiceTime, Trip};
+use crate::output::{self, PlanLegRecord};
+use crate::schedule::Data;
+
+/// The most transfers a journey may have.
+pub const MAX_TRANSFERS: usize = 4;
+
+/// How far from the requested time a journey may end, in seconds: arriving after leaving, or
+/// leaving before arriving by.
+pub const MAX_JOURNEY_TIME: i64 = 12 * 3600;
+
+/// When a journey should happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanTime {
+    /// Leave at or after this time.
+    Depart(NaiveDateTime),
+    /// Arrive at or before this time.
+    ArriveBy(NaiveDateTime),
+}
+
+/// One leg of a [`Journey`]: a ride on a bus, or a walk between stops.
+pub struct Leg {
+    /// Whether this is a walk. Walks have no route, headsign or trip.
+    pub walking: bool,
+    /// The route's short name, e.g. `80`.
+    pub route: String,
+    pub headsign: String,
+    pub trip_id: String,
+    pub from_stop_id: String,
+    pub from_stop_name: String,
+    pub departure: NaiveDateTime,
+    pub to_stop_id: String,
+    pub to_stop_name: String,
+    pub arrival: NaiveDateTime,
+}
+
+/// A way to get from one stop to another, from [`Data::plan`].
+pub struct Journey {
+    pub legs: Vec<Leg>,
+}
+
+impl Journey {
+    /// When the journey starts.
+    pub fn departure(&self) -> NaiveDateTime {
+        self.legs.first().expect("Journey without legs").departure
+    }
+
+    /// When the journey ends.
+    pub fn arrival(&self) -> NaiveDateTime {
+        self.legs.last().expect("Journey without legs").arrival
+    }
+
+    /// How many times the rider changes buses.
+    pub fn transfers(&self) -> usize {
+        self.legs
+            .iter()
+            .filter(|leg| !leg.walking)
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// Flatten into one record per leg, for `--format`. `journey` numbers the journey among the
+    /// ones planned.
+    pub fn records(&self, journey: usize) -> Vec<PlanLegRecord<'_>> {
+        self.legs
+            .iter()
+            .enumerate()
+            .map(|(i, leg)| PlanLegRecord {
+                journey,
+                transfers: self.transfers(),
+                leg: i + 1,
+                mode: if leg.walking { "walk" } else { "bus" },
+                route: &leg.route,
+                headsign: &leg.headsign,
+                trip_id: &leg.trip_id,
+                from_stop_id: &leg.from_stop_id,
+                from_stop_name: &leg.from_stop_name,
+                departure_time: leg.departure.format(output::TIME_FORMAT).to_string(),
+                to_stop_id: &leg.to_stop_id,
+                to_stop_name: &leg.to_stop_name,
+                arrival_time: leg.arrival.format(output::TIME_FORMAT).to_string(),
+            })
+            .collect()
+    }
+}
+
+/// A trip on one service day, with its times in seconds from midnight of the day being planned.
+///
+/// For a backwards search, everything is mirrored: the stops are in reverse order, the times are
+/// negated, boarding and alighting swap places, and walks go the other way. The earliest arrival
+/// in the mirrored network is then the latest departure in the real one.
+struct PatternTrip<'d> {
+    trip: &'d Trip,
+    arrivals: Vec<i64>,
+    departures: Vec<i64>,
+    can_board: Vec<bool>,
+    can_alight: Vec<bool>,
+}
+
+/// The trips of a route that all make the same stops in the same order.
+struct Pattern<'d> {
+    stops: Vec<usize>,
+    trips: Vec<PatternTrip<'d>>,
+}
+
+/// The part of the schedule a search runs over, with stops numbered.
+struct Network<'d> {
+    stop_ids: Vec<&'d str>,
+    stop_index: HashMap<&'d str, usize>,
+    patterns: Vec<Pattern<'d>>,
+    /// For each stop, the patterns through it as `(pattern, position)`.
+    stop_patterns: Vec<Vec<(usize, usize)>>,
+    /// For each stop, the stops within a walk as `(stop, seconds)`.
+    walks: Vec<Vec<(usize, i64)>>,
+    /// For each stop, the seconds needed to change buses there.
+    slack: Vec<i64>,
+}
+
+/// How a stop was reached in some round.
+#[derive(Debug, Clone, Copy)]
+enum Label {
+    /// On a trip of a pattern, boarded and left at these positions.
+    Ride {
+        pattern: usize,
+        trip: usize,
+        board: usize,
+        alight: usize,
+    },
+    /// On foot from another stop reached in the same round.
+    Walk { from: usize, duration: i64 },
+}
+
+/// A leg of a journey as the search sees it, possibly mirrored.
+struct Step {
+    from: usize,
+    to: usize,
+    departure: i64,
+    arrival: i64,
+    /// The pattern and trip ridden, or `None` for a walk.
+    trip: Option<(usize, usize)>,
+}
+
+impl<'d> Network<'d> {
+    /// Gather the trips running on the service days around `day`, grouped into patterns, and the
+    /// walks between their stops. The `endpoints` are included even if no bus stops there, so that
+    /// they can be walked to.
+    fn new(data: &'d Data, day: NaiveDate, backwards: bool, endpoints: &[&'d str]) -> Self {
+        let mut network = Network {
+            stop_ids: vec![],
+            stop_index: HashMap::new(),
+            patterns: vec![],
+            stop_patterns: vec![],
+            walks: vec![],
+            slack: vec![],
+        };
+        for stop_id in endpoints {
+            network.stop(stop_id);
+        }
+        let mut pattern_index: HashMap<(&str, Vec<usize>), usize> = HashMap::new();
+        let sign = if backwards { -1 } else { 1 };
+
+        for trip in data.trips.values() {
+            let service = match data.calendar.get(&trip.service_id) {
+                Some(service) => service,
+                None => continue,
+            };
+
+            // Stops without times can't be planned around.
+            let mut stop_times: Vec<_> = data
+                .trip_stop_times(&trip.trip_id)
+                .filter(|stop_time| {
+                    stop_time.arrival_time.is_some() && stop_time.departure_time.is_some()
+                })
+                .collect();
+            if stop_times.len() < 2 {
+                continue;
+            }
+            if backwards {
+                stop_times.reverse();
+            }
+
+            let stops: Vec<_> = stop_times
+                .iter()
+                .map(|stop_time| network.stop(&stop_time.stop_id))
+                .collect();
+            // GTFS `1` means no pickup or no drop off.
+            let pickup: Vec<_> = stop_times.iter().map(|st| st.pickup_type != "1").collect();
+            let drop_off: Vec<_> = stop_times
+                .iter()
+                .map(|st| st.drop_off_type != "1")
+                .collect();
+
+            for offset in -1..=1 {
+                let service_day = day + chrono::Duration::days(offset);
+                if !service.runs_on(service_day) {
+                    continue;
+                }
+
+                let seconds = |time: Option<ServiceTime>| {
+                    sign * (offset * 86400 + time.unwrap().since_start_of_day().num_seconds())
+                };
+                let arrivals = stop_times.iter().map(|st| seconds(st.arrival_time));
+                let departures = stop_times.iter().map(|st| seconds(st.departure_time));
+                let pattern_trip = if backwards {
+                    PatternTrip {
+                        trip,
+                        arrivals: departures.collect(),
+                        departures: arrivals.collect(),
+                        can_board: drop_off.clone(),
+                        can_alight: pickup.clone(),
+                    }
+                } else {
+                    PatternTrip {
+                        trip,
+                        arrivals: arrivals.collect(),
+                        departures: departures.collect(),
+                        can_board: pickup.clone(),
+                        can_alight: drop_off.clone(),
+                    }
+                };
+
+                let patterns = &mut network.patterns;
+                let pattern = *pattern_index
+                    .entry((&trip.route_id, stops.clone()))
+                    .or_insert_with(|| {
+                        patterns.push(Pattern {
+                            stops: stops.clone(),
+                            trips: vec![],
+                        });
+                        patterns.len() - 1
+                    });
+                network.patterns[pattern].trips.push(pattern_trip);
+            }
+        }
+
+        let n = network.stop_ids.len();
+        network.stop_patterns = vec![vec![]; n];
+        for (p, pattern) in network.patterns.iter().enumerate() {
+            for (i, &stop) in pattern.stops.iter().enumerate() {
+                network.stop_patterns[stop].push((p, i));
+            }
+        }
+
+        network.walks = vec![vec![]; n];
+        network.slack = vec![0; n];
+        for from in 0..n {
+            for footpath in data.footpaths(network.stop_ids[from]) {
+                let to = match network.stop_index.get(footpath.to_stop_id.as_str()) {
+                    Some(&to) => to,
+                    None => continue,
+                };
+                let duration = footpath.duration.num_seconds();
+                if to == from {
+                    network.slack[from] = duration;
+                } else if backwards {
+                    network.walks[to].push((from, duration));
+                } else {
+                    network.walks[from].push((to, duration));
+                }
+            }
+        }
+
+        network
+    }
+
+    /// The number of a stop, numbering it if it is new.
+    fn stop(&mut self, stop_id: &'d str) -> usize {
+        if let Some(&stop) = self.stop_index.get(stop_id) {
+            return stop;
+        }
+        self.stop_ids.push(stop_id);
+        self.stop_index.insert(stop_id, self.stop_ids.len() - 1);
+        self.stop_ids.len() - 1
+    }
+
+    /// Run the rounds from `source` at time `start`. Returns the steps of each journey to
+    /// `target` that arrives earlier than any with fewer rides.
+    fn search(&self, source: usize, target: usize, start: i64) -> Vec<Vec<Step>> {
+        let n = self.stop_ids.len();
+
+        // By round, the earliest arrival at each stop using at most that many trips, and how.
+        let mut arrivals = vec![vec![i64::MAX; n]];
+        let mut labels: Vec<Vec<Option<Label>>> = vec![vec![None; n]];
+        arrivals[0][source] = start;
+
+        // Nothing reaching the target later than this is worth finding.
+        let mut best = vec![i64::MAX; n];
+        best[target] = start + MAX_JOURNEY_TIME;
+        best[source] = start;
+        let mut marked = vec![source];
+        marked.extend(self.walk_from(
+            &[source],
+            &mut arrivals[0],
+            &mut labels[0],
+            &mut best,
+            target,
+        ));
+
+        let mut journeys = vec![];
+        if arrivals[0][target] < i64::MAX {
+            journeys.push(self.steps_to(&arrivals, &labels, 0, source, target));
+        }
+
+        for k in 1..=MAX_TRANSFERS + 1 {
+            let previous = &arrivals[k - 1];
+            let previous_labels = &labels[k - 1];
+            let mut round_arrivals = previous.clone();
+            let mut round_labels = previous_labels.clone();
+
+            // Scan each pattern through a stop improved last round, from the earliest such stop.
+            let mut queue: HashMap<usize, usize> = HashMap::new();
+            for &stop in marked.iter() {
+                for &(pattern, i) in self.stop_patterns[stop].iter() {
+                    let first = queue.entry(pattern).or_insert(i);
+                    *first = (*first).min(i);
+                }
+            }
+
+            let mut newly_marked = vec![];
+            for (p, first) in queue {
+                let pattern = &self.patterns[p];
+                let mut current: Option<(usize, usize)> = None; // (trip, boarded at)
+
+                for i in first..pattern.stops.len() {
+                    let stop = pattern.stops[i];
+
+                    if let Some((t, board)) = current {
+                        let trip = &pattern.trips[t];
+                        let arrival = trip.arrivals[i];
+                        if trip.can_alight[i] && arrival < best[stop].min(best[target]) {
+                            round_arrivals[stop] = arrival;
+                            best[stop] = arrival;
+                            round_labels[stop] = Some(Label::Ride {
+                                pattern: p,
+                                trip: t,
+                                board,
+                                alight: i,
+                            });
+                            newly_marked.push(stop);
+                        }
+                    }
+
+                    // Catch an earlier trip here if we could be here in time for it. Getting off
+                    // one bus and onto another takes the stop's transfer time.
+                    if previous[stop] == i64::MAX {
+                        continue;
+                    }
+                    let ready = match previous_labels[stop] {
+                        Some(Label::Ride { .. }) => previous[stop] + self.slack[stop],
+                        _ => previous[stop],
+                    };
+                    if current.is_some_and(|(t, _)| ready > pattern.trips[t].departures[i]) {
+                        continue;
+                    }
+                    let earliest = pattern
+                        .trips
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, trip)| trip.can_board[i] && trip.departures[i] >= ready)
+                        .min_by_key(|(_, trip)| trip.departures[i]);
+                    if let Some((t, trip)) = earliest {
+                        if current.is_none_or(|(c, _)| {
+                            trip.departures[i] < pattern.trips[c].departures[i]
+                        }) {
+                            current = Some((t, i));
+                        }
+                    }
+                }
+            }
+
+            newly_marked.sort_unstable();
+            newly_marked.dedup();
+            let walked = self.walk_from(
+                &newly_marked,
+                &mut round_arrivals,
+                &mut round_labels,
+                &mut best,
+                target,
+            );
+            newly_marked.extend(walked);
+
+            let improved = round_arrivals[target] < previous[target];
+            arrivals.push(round_arrivals);
+            labels.push(round_labels);
+
+            if improved {
+                journeys.push(self.steps_to(&arrivals, &labels, k, source, target));
+            }
+
+            newly_marked.sort_unstable();
+            newly_marked.dedup();
+            if newly_marked.is_empty() {
+                break;
+            }
+            marked = newly_marked;
+        }
+
+        journeys
+    }
+
+    /// Walk on from the given stops, as they were reached. Returns the stops this reaches sooner.
+    fn walk_from(
+        &self,
+        stops: &[usize],
+        arrivals: &mut [i64],
+        labels: &mut [Option<Label>],
+        best: &mut [i64],
+        target: usize,
+    ) -> Vec<usize> {
+        let starts: Vec<_> = stops.iter().map(|&stop| (stop, arrivals[stop])).collect();
+
+        let mut reached = vec![];
+        for (from, start) in starts {
+            for &(to, duration) in self.walks[from].iter() {
+                let arrival = start + duration;
+                if arrival < best[to].min(best[target]) {
+                    arrivals[to] = arrival;
+                    best[to] = arrival;
+                    labels[to] = Some(Label::Walk { from, duration });
+                    reached.push(to);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Follow the labels back from `target` in round `k` to `source`.
+    fn steps_to(
+        &self,
+        arrivals: &[Vec<i64>],
+        labels: &[Vec<Option<Label>>],
+        k: usize,
+        source: usize,
+        target: usize,
+    ) -> Vec<Step> {
+        let mut steps = vec![];
+        let (mut stop, mut round) = (target, k);
+        while stop != source {
+            match labels[round][stop].expect("Stop reached without a label") {
+                Label::Walk { from, duration } => {
+                    let arrival = arrivals[round][stop];
+                    steps.push(Step {
+                        from,
+                        to: stop,
+                        departure: arrival - duration,
+                        arrival,
+                        trip: None,
+                    });
+                    stop = from;
+                }
+                Label::Ride {
+                    pattern,
+                    trip,
+                    board,
+                    alight,
+                } => {
+                    let from = self.patterns[pattern].stops[board];
+                    let pattern_trip = &self.patterns[pattern].trips[trip];
+                    steps.push(Step {
+                        from,
+                        to: stop,
+                        departure: pattern_trip.departures[board],
+                        arrival: pattern_trip.arrivals[alight],
+                        trip: Some((pattern, trip)),
+                    });
+                    stop = from;
+                    round -= 1;
+                }
+            }
+        }
+        steps.reverse();
+        steps
+    }
+}
+
+impl Data {
+    /// Plan journeys from one stop to another on the static schedule, walking between nearby
+    /// stops as [`Data::footpaths`] allows. Returns the best journey for each number of transfers
+    /// (up to [`MAX_TRANSFERS`]) that beats every journey with fewer: the earliest arrival when
+    /// departing, or the latest departure when arriving by a deadline. Journeys ending more than
+    /// [`MAX_JOURNEY_TIME`] from the requested time are left out.
+    pub fn plan(
+        &self,
+        from: &str,
+        to: &str,
+        when: PlanTime,
+    ) -> Result<Vec<Journey>, anyhow::Error> {
+        let (from, to) = match (self.stops.get(from), self.stops.get(to)) {
+            (Some(from), Some(to)) => (from.stop_id.as_str(), to.stop_id.as_str()),
+            (None, _) => bail!("No such bus stop: {}", from),
+            (_, None) => bail!("No such bus stop: {}", to),
+        };
+        if from == to {
+            return Ok(vec![]);
+        }
+
+        let (time, backwards) = match when {
+            PlanTime::Depart(time) => (time, false),
+            PlanTime::ArriveBy(time) => (time, true),
+        };
+        let day = time.date();
+        let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+        let seconds = (time - midnight).num_seconds();
+
+        let network = Network::new(self, day, backwards, &[from, to]);
+        let (source, target, start) = if backwards {
+            (network.stop_index[to], network.stop_index[from], -seconds)
+        } else {
+            (network.stop_index[from], network.stop_index[to], seconds)
+        };
+
+        let stop_name = |stop: usize| {
+            let stop_id = network.stop_ids[stop];
+            let stop_name = self
+                .stops
+                .get(stop_id)
+                .map(|stop| stop.stop_name.clone())
+                .unwrap_or_default();
+            (stop_id.to_owned(), stop_name)
+        };
+        let at = |time: i64| midnight + chrono::Duration::seconds(time);
+
+        let journeys = network
+            .search(source, target, start)
+            .into_iter()
+            .map(|steps| {
+                let mut legs: Vec<_> = steps
+                    .iter()
+                    .map(|step| {
+                        // Unmirror a backwards step: it goes the other way, at negated times.
+                        let (
+                            (from_stop_id, from_stop_name),
+                            departure,
+                            (to_stop_id, to_stop_name),
+                            arrival,
+                        ) = if backwards {
+                            (
+                                stop_name(step.to),
+                                at(-step.arrival),
+                                stop_name(step.from),
+                                at(-step.departure),
+                            )
+                        } else {
+                            (
+                                stop_name(step.from),
+                                at(step.departure),
+                                stop_name(step.to),
+                                at(step.arrival),
+                            )
+                        };
+                        let trip = step
+                            .trip
+                            .map(|(pattern, trip)| network.patterns[pattern].trips[trip].trip);
+
+                        Leg {
+                            walking: trip.is_none(),
+                            route: trip
+                                .map_or_else(String::new, |trip| trip.route_short_name.clone()),
+                            headsign: trip
+                                .map_or_else(String::new, |trip| trip.trip_headsign.clone()),
+                            trip_id: trip.map_or_else(String::new, |trip| trip.trip_id.clone()),
+                            from_stop_id,
+                            from_stop_name,
+                            departure,
+                            to_stop_id,
+                            to_stop_name,
+                            arrival,
+                        }
+                    })
+                    .collect();
+                if backwards {
+                    legs.reverse();
+                }
+                Journey { legs }
+            })
+            .collect();
+
+        Ok(journeys)
+    }
+}
@@ -0,0 +1,304 @@
+//! Real-time trip updates: fetching them, decoding them, and working out the delay of each bus.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::gtfs_rt;
+
+/// Real-time delays from the trip updates feed, in seconds. Negative delays mean the bus is early.
+#[derive(Debug, Clone, Default)]
+pub struct RealTimeInfo {
+    /// {stop_id: {trip_id: delay}}
+    pub by_stop: HashMap<String, HashMap<String, f64>>,
+    /// {trip_id: [(stop_sequence, delay)]}, in stop_sequence order. The delay is `None` from a
+    /// stop the feed has no data for, up to the next one it has.
+    pub by_trip: HashMap<String, Vec<(u32, Option<f64>)>>,
+    /// {trip_id: [(stop_id, delay)]} for updates that give the stop but not its stop_sequence,
+    /// in feed order. Only the schedule knows where these stops are on the trip.
+    pub unsequenced: HashMap<String, Vec<(String, Option<f64>)>>,
+    /// trip_ids of trips that have been cancelled.
+    pub cancelled: HashSet<String>,
+    /// (trip_id, stop_id) of stops that a trip will not serve.
+    pub skipped: HashSet<(String, String)>,
+    /// {stop_id: \[stop\]} for extra trips added in real time.
+    pub added: HashMap<String, Vec<AddedStop>>,
+    /// When the feed was made, if it says.
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// A stop of an extra trip that only exists in the real-time data.
+#[derive(Debug, Clone)]
+pub struct AddedStop {
+    pub trip_id: String,
+    pub route_id: Option<String>,
+    /// The predicted departure.
+    pub departure: DateTime<Utc>,
+}
+
+impl RealTimeInfo {
+    /// The delay of the trip at the given stop. If the feed has no prediction for that stop, the
+    /// delay at the closest earlier stop on the trip carries forward, as the GTFS-RT spec says.
+    /// That needs updates with a stop_sequence; [`Data::real_time_delay`] handles those without.
+    ///
+    /// [`Data::real_time_delay`]: crate::Data::real_time_delay
+    pub fn delay(&self, trip_id: &str, stop_id: &str, stop_sequence: Option<u32>) -> Option<f64> {
+        if let Some(delay) = self
+            .by_stop
+            .get(stop_id)
+            .and_then(|trips| trips.get(trip_id))
+        {
+            return Some(*delay);
+        }
+
+        let stop_sequence = stop_sequence?;
+        self.by_trip
+            .get(trip_id)?
+            .iter()
+            .take_while(|(sequence, _)| *sequence <= stop_sequence)
+            .last()
+            .and_then(|(_, delay)| *delay)
+    }
+}
+
+macro_rules! warn_and_skip {
+    ($json:ident, $key:literal) => {{
+        if $json.has_key($key) {
+            $json.remove($key)
+        } else {
+            eprintln!("Key {} not found in {}", $key, stringify!($json));
+            continue;
+        }
+    }};
+}
+
+// Enums may be rendered in JSON by either name or number.
+fn json_enum<E: Into<i32>>(
+    value: &json::JsonValue,
+    from_name: fn(&str) -> Option<E>,
+) -> Option<i32> {
+    value
+        .as_str()
+        .and_then(from_name)
+        .map(Into::into)
+        .or_else(|| value.as_i32())
+}
+
+// Hack your way through Metro's JSON rendering of the real time data and produce the same typed
+// feed that the protobuf decoder does.
+fn parse_real_time_json(
+    mut real_time_json: json::JsonValue,
+) -> Result<gtfs_rt::FeedMessage, anyhow::Error> {
+    if !real_time_json.has_key("entity") {
+        bail!("No entities in real-time data");
+    }
+
+    let mut feed = gtfs_rt::FeedMessage::default();
+    feed.header.timestamp = real_time_json["header"]["timestamp"].as_u64();
+
+    let mut entity = real_time_json.remove("entity");
+    for update in entity.members_mut() {
+        let mut trip_update = warn_and_skip!(update, "trip_update");
+        let mut trip = warn_and_skip!(trip_update, "trip");
+        // Cancelled trips may not have any stop time updates.
+        let mut stop_time_update = trip_update.remove("stop_time_update");
+        let trip_id = warn_and_skip!(trip, "trip_id")
+            .as_str()
+            .expect("expected str")
+            .to_owned();
+        let mut stop_time_updates = vec![];
+        for stop_time in stop_time_update.members_mut() {
+            let stop_id = warn_and_skip!(stop_time, "stop_id")
+                .as_str()
+                .expect("expected str")
+                .to_owned();
+            let event = |key| {
+                if stop_time.has_key(key) {
+                    Some(gtfs_rt::StopTimeEvent {
+                        delay: stop_time[key]["delay"].as_f64().map(|delay| delay as i32),
+                        time: stop_time[key]["time"].as_i64(),
+                        ..Default::default()
+                    })
+                } else {
+                    None
+                }
+            };
+
+            stop_time_updates.push(gtfs_rt::StopTimeUpdate {
+                stop_sequence: stop_time["stop_sequence"].as_u32(),
+                stop_id: Some(stop_id),
+                arrival: event("arrival"),
+                departure: event("departure"),
+                schedule_relationship: json_enum(
+                    &stop_time["schedule_relationship"],
+                    gtfs_rt::stop_time_update::ScheduleRelationship::from_name,
+                ),
+            });
+        }
+
+        feed.entity.push(gtfs_rt::FeedEntity {
+            trip_update: Some(gtfs_rt::TripUpdate {
+                trip: gtfs_rt::TripDescriptor {
+                    trip_id: Some(trip_id),
+                    route_id: trip["route_id"].as_str().map(String::from),
+                    schedule_relationship: json_enum(
+                        &trip["schedule_relationship"],
+                        gtfs_rt::trip_descriptor::ScheduleRelationship::from_name,
+                    ),
+                    ..Default::default()
+                },
+                stop_time_update: stop_time_updates,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+    Ok(feed)
+}
+
+/// Decode real-time data, which may be either the binary GTFS-RT protobuf most agencies publish
+/// or Metro's JSON rendering of it.
+pub fn parse_real_time_feed(raw: &[u8]) -> Result<gtfs_rt::FeedMessage, anyhow::Error> {
+    let raw = raw.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(raw);
+    let parse_json = || parse_real_time_json(json::parse(std::str::from_utf8(raw)?)?);
+
+    // Every protobuf feed starts with its header, field 1, so its first byte is always 0x0A. That
+    // is a newline, so JSON may only be recognised by its very first byte. JSON with leading
+    // whitespace still gets a go if it doesn't decode as protobuf.
+    if raw.first() == Some(&b'{') {
+        return parse_json();
+    }
+    match gtfs_rt::decode(raw) {
+        Ok(feed) => Ok(feed),
+        Err(_) if raw.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') => parse_json(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Produce by-stop-by-trip delay info from the real time data. A stop without its own delay gets
+/// the last delay seen earlier on the trip. Cancelled trips, skipped stops and added trips are
+/// collected separately.
+pub fn parse_real_time_data(feed: &gtfs_rt::FeedMessage) -> RealTimeInfo {
+    use gtfs_rt::stop_time_update::ScheduleRelationship as StopRelationship;
+    use gtfs_rt::trip_descriptor::ScheduleRelationship as TripRelationship;
+
+    let mut real_time = RealTimeInfo {
+        timestamp: feed
+            .header
+            .timestamp
+            // Some feeds leave it at 0 rather than out.
+            .filter(|time| *time > 0)
+            .and_then(|time| Utc.timestamp_opt(time as i64, 0).single()),
+        ..Default::default()
+    };
+
+    for entity in feed.entity.iter() {
+        let trip_update = match &entity.trip_update {
+            Some(trip_update) => trip_update,
+            None => continue,
+        };
+        let trip_id = match &trip_update.trip.trip_id {
+            Some(trip_id) => trip_id,
+            None => continue,
+        };
+
+        match trip_update.trip.schedule_relationship() {
+            TripRelationship::Canceled | TripRelationship::Deleted => {
+                real_time.cancelled.insert(trip_id.clone());
+                continue;
+            }
+            TripRelationship::Added => {
+                // An extra trip has no schedule to be delayed from, so it should come with
+                // predicted times instead.
+                for stop_time in trip_update.stop_time_update.iter() {
+                    let time = stop_time
+                        .departure
+                        .as_ref()
+                        .and_then(|departure| departure.time)
+                        .or_else(|| stop_time.arrival.as_ref().and_then(|arrival| arrival.time))
+                        .and_then(|time| Utc.timestamp_opt(time, 0).single());
+                    if let (Some(stop_id), Some(time)) = (&stop_time.stop_id, time) {
+                        real_time
+                            .added
+                            .entry(stop_id.clone())
+                            .or_default()
+                            .push(AddedStop {
+                                trip_id: trip_id.clone(),
+                                route_id: trip_update.trip.route_id.clone(),
+                                departure: time,
+                            });
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Feeds list updates in trip order. Without a stop_sequence on every one, that order is
+        // all there is to go on.
+        let mut stop_time_updates: Vec<_> = trip_update.stop_time_update.iter().collect();
+        if stop_time_updates
+            .iter()
+            .all(|stop_time| stop_time.stop_sequence.is_some())
+        {
+            stop_time_updates.sort_by_key(|stop_time| stop_time.stop_sequence);
+        }
+
+        let mut rolling_delay = trip_update.delay.map(f64::from);
+        for stop_time in stop_time_updates {
+            let delay = match stop_time.schedule_relationship() {
+                StopRelationship::Skipped => {
+                    // The delay still carries on to the stops after this one.
+                    if let Some(stop_id) = &stop_time.stop_id {
+                        real_time.skipped.insert((trip_id.clone(), stop_id.clone()));
+                    }
+                    continue;
+                }
+                // There are no predictions from here until the next update with data, so this
+                // is recorded to stop the earlier delay carrying on.
+                StopRelationship::NoData => None,
+                _ => stop_time
+                    .departure
+                    .as_ref()
+                    .and_then(|departure| departure.delay)
+                    .or_else(|| stop_time.arrival.as_ref().and_then(|arrival| arrival.delay))
+                    .map(f64::from)
+                    .or(rolling_delay),
+            };
+            rolling_delay = delay;
+
+            if let (Some(stop_id), Some(delay)) = (&stop_time.stop_id, delay) {
+                real_time
+                    .by_stop
+                    .entry(stop_id.clone())
+                    .or_default()
+                    .insert(trip_id.clone(), delay);
+            }
+            match (stop_time.stop_sequence, &stop_time.stop_id) {
+                (Some(stop_sequence), _) => real_time
+                    .by_trip
+                    .entry(trip_id.clone())
+                    .or_default()
+                    .push((stop_sequence, delay)),
+                (None, Some(stop_id)) => real_time
+                    .unsequenced
+                    .entry(trip_id.clone())
+                    .or_default()
+                    .push((stop_id.clone(), delay)),
+                (None, None) => {}
+            }
+        }
+    }
+    real_time
+}
+
+/// Fetch and decode the real-time trip updates from the given address.
+pub fn fetch_real_time_data(trip_update_url: &str) -> Result<RealTimeInfo, anyhow::Error> {
+    let real_time_raw = reqwest::blocking::get(trip_update_url)
+        .and_then(|r| r.bytes())
+        .context("Unable to fetch real-time data.")?;
+    let feed = parse_real_time_feed(&real_time_raw).context("Unable to parse real-time data.")?;
+    Ok(parse_real_time_data(&feed))
+}
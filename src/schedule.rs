@@ -0,0 +1,866 @@
+//! Answering questions about the schedule: what comes to a stop, and which stops are nearby.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use chrono_tz::Tz;
+
+use crate::geo::{self, GridIndex, LatLon};
+use crate::gtfs::{
+    Accessibility, Agency, Calendar, CalendarDate, CalendarDateRaw, GtfsSource, Route, Stop,
+    StopTime, Transfer, Trip,
+};
+use crate::output::{self, DepartureRecord, NearbyStopRecord, RouteRecord};
+use crate::realtime::RealTimeInfo;
+use crate::walking::Walking;
+use crate::when;
+
+/// Whether a bus is running as scheduled, according to the real-time data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusStatus {
+    Scheduled,
+    /// The whole trip has been cancelled.
+    Cancelled,
+    /// The trip runs, but will not stop here.
+    Skipped,
+    /// An extra trip that is not in the static schedule.
+    Added,
+}
+
+impl BusStatus {
+    /// A stable lowercase name, e.g. for machine-readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            BusStatus::Scheduled => "scheduled",
+            BusStatus::Cancelled => "cancelled",
+            BusStatus::Skipped => "skipped",
+            BusStatus::Added => "added",
+        }
+    }
+}
+
+/// A bus coming to a stop.
+pub struct BusInfo {
+    /// The stop the bus comes to, which may be any of the stops of a station.
+    pub stop_id: String,
+    /// Tells the stop apart from the others listed; see [`Stop::platform`].
+    pub platform: String,
+    /// The route's short name, e.g. `80`.
+    pub route: String,
+    pub headsign: String,
+    pub trip_id: String,
+    /// The scheduled departure time, or the arrival time when listing arrivals.
+    pub departure: NaiveDateTime,
+    /// Real-time delay in seconds, if known.
+    pub delay: Option<f64>,
+    pub status: BusStatus,
+    /// Whether the trip ends here.
+    pub last_stop: bool,
+    pub wheelchair: Accessibility,
+    pub bikes: Accessibility,
+}
+
+impl BusInfo {
+    /// When the bus should actually leave, going by the real-time data.
+    pub fn predicted_departure(&self) -> NaiveDateTime {
+        self.departure + chrono::Duration::seconds(self.delay.unwrap_or(0.0) as i64)
+    }
+}
+
+/// The upcoming buses at a stop, from [`Data::stop_sched`].
+pub struct StopBusInfo {
+    /// The stop asked about, or its station if the stops of the station were listed together.
+    pub stop_id: String,
+    pub stop_name: String,
+    /// The stops whose buses are listed.
+    pub stop_ids: Vec<String>,
+    /// Whether a rider in a wheelchair can board at the stop.
+    pub wheelchair_boarding: Accessibility,
+    pub buses: Vec<BusInfo>,
+}
+
+impl StopBusInfo {
+    /// Flatten into one record per bus, for `--format`.
+    pub fn records(&self) -> Vec<DepartureRecord<'_>> {
+        self.buses
+            .iter()
+            .map(|bus| DepartureRecord {
+                stop_id: &bus.stop_id,
+                stop_name: &self.stop_name,
+                platform: &bus.platform,
+                route: &bus.route,
+                headsign: &bus.headsign,
+                status: bus.status.name(),
+                scheduled_time: bus.departure.format(output::TIME_FORMAT).to_string(),
+                delay_seconds: bus.delay.map(|delay| delay as i64),
+                predicted_time: bus
+                    .predicted_departure()
+                    .format(output::TIME_FORMAT)
+                    .to_string(),
+                trip_id: &bus.trip_id,
+                last_stop: bus.last_stop,
+                wheelchair_accessible: bus.wheelchair.name(),
+                bikes_allowed: bus.bikes.name(),
+            })
+            .collect()
+    }
+}
+
+/// Which buses [`Data::stop_sched`] should list. Start with [`FilterConfig::new`] and narrow it
+/// down with the other methods.
+pub struct FilterConfig<'s> {
+    /// Stop ID
+    stop_id: &'s str,
+
+    /// List buses at or after `after`, or now if not given
+    after: Option<NaiveDateTime>,
+
+    /// How many buses to list?
+    how_many: Option<usize>,
+
+    /// Which route to list? If none, list all.
+    route: Option<&'s str>,
+
+    /// List arrivals instead of departures?
+    arrivals: bool,
+
+    /// List the buses at every stop of the stop's station?
+    station: bool,
+
+    /// Also list the buses at stops within walking distance?
+    nearby: bool,
+
+    /// List only buses that take wheelchairs, at stops where wheelchairs can board?
+    wheelchair: bool,
+
+    /// List only buses that take bikes?
+    bikes: bool,
+}
+
+impl<'s> FilterConfig<'s> {
+    /// All buses at the given stop from now on, in the agency's time zone.
+    pub fn new(stop_id: &'s str) -> FilterConfig<'s> {
+        Self {
+            stop_id,
+            after: None,
+            how_many: None,
+            route: None,
+            arrivals: false,
+            station: false,
+            nearby: false,
+            wheelchair: false,
+            bikes: false,
+        }
+    }
+
+    /// List buses at or after the given time, in the agency's time zone, instead of now.
+    pub fn after(self, after: NaiveDateTime) -> Self {
+        Self {
+            after: Some(after),
+            ..self
+        }
+    }
+
+    /// List at most this many buses.
+    pub fn how_many(self, how_many: usize) -> Self {
+        Self {
+            how_many: Some(how_many),
+            ..self
+        }
+    }
+
+    /// List only buses on the route with this short name.
+    pub fn route(self, route: &'s str) -> Self {
+        Self {
+            route: Some(route),
+            ..self
+        }
+    }
+
+    /// List buses arriving rather than leaving: by arrival time, including trips that end here
+    /// but not ones that start here or don't let riders off.
+    pub fn arrivals(self) -> Self {
+        Self {
+            arrivals: true,
+            ..self
+        }
+    }
+
+    /// List the buses at every stop of the station, if the stop is a station or part of one.
+    pub fn station(self) -> Self {
+        Self {
+            station: true,
+            ..self
+        }
+    }
+
+    /// Also list the buses at the stops within [`Data::walking`]'s radius of the stop, or of its
+    /// station's stops.
+    pub fn nearby(self) -> Self {
+        Self {
+            nearby: true,
+            ..self
+        }
+    }
+
+    /// List only trips the feed marks as wheelchair accessible, and leave out stops it marks as
+    /// not accessible.
+    pub fn wheelchair(self) -> Self {
+        Self {
+            wheelchair: true,
+            ..self
+        }
+    }
+
+    /// List only trips the feed marks as allowing bikes.
+    pub fn bikes(self) -> Self {
+        Self {
+            bikes: true,
+            ..self
+        }
+    }
+
+    /// Does a bus with the given route short name pass the route filter?
+    pub fn matches_route(&self, route_short_name: &str) -> bool {
+        self.route
+            .is_none_or(|route| same_route(route_short_name, route))
+    }
+}
+
+/// Do the two route short names refer to the same route?
+pub fn same_route(route_short_name: &str, route: &str) -> bool {
+    // If the short name is a number, then we don't want to mismatch because of
+    // a leading 0... e.g. route 08 and route 8 should match.
+    route_short_name.trim_start_matches('0') == route.trim_start_matches('0')
+}
+
+/// A stop near some place, from [`Data::near`].
+pub struct NearbyStop<'d> {
+    pub stop: &'d Stop,
+    /// In meters.
+    pub distance: f64,
+    /// From the place to the stop, in degrees clockwise from north.
+    pub bearing: f64,
+}
+
+impl NearbyStop<'_> {
+    /// Flatten into a record, for `--format`, with the next bus at the stop if there is one.
+    pub fn record<'a>(&'a self, next: Option<&'a BusInfo>) -> NearbyStopRecord<'a> {
+        NearbyStopRecord {
+            stop_id: &self.stop.stop_id,
+            stop_code: &self.stop.stop_code,
+            stop_name: &self.stop.stop_name,
+            distance_meters: self.distance.round() as u64,
+            bearing_degrees: self.bearing.round() as u64 % 360,
+            compass: geo::compass_point(self.bearing),
+            cardinal_direction: &self.stop.cardinal_direction,
+            next_route: next.map(|bus| bus.route.as_str()),
+            next_headsign: next.map(|bus| bus.headsign.as_str()),
+            next_predicted_time: next.map(|bus| {
+                bus.predicted_departure()
+                    .format(output::TIME_FORMAT)
+                    .to_string()
+            }),
+        }
+    }
+}
+
+/// A route running on some day, from [`Data::routes_on`].
+pub struct RouteSummary<'d> {
+    pub route_id: String,
+    pub route_short_name: String,
+    /// The route from `routes.txt`, if the feed has it.
+    pub route: Option<&'d Route>,
+    /// When the first trip of the day leaves its first stop.
+    pub first_departure: NaiveDateTime,
+    /// When the last trip of the day gets to its last stop.
+    pub last_arrival: NaiveDateTime,
+    /// The names of the services the route runs under that day, e.g. `Weekday`.
+    pub service_names: Vec<String>,
+}
+
+impl RouteSummary<'_> {
+    /// Flatten into a record, for `--format`.
+    pub fn record(&self) -> RouteRecord<'_> {
+        RouteRecord {
+            route_id: &self.route_id,
+            route_short_name: &self.route_short_name,
+            route_long_name: self.route.map_or("", |route| &route.route_long_name),
+            route_type: self.route.map_or("bus", |route| route.route_type.name()),
+            route_color: self.route.and_then(|route| route.route_color.as_deref()),
+            route_text_color: self
+                .route
+                .and_then(|route| route.route_text_color.as_deref()),
+            first_departure: self.first_departure.format(output::TIME_FORMAT).to_string(),
+            last_arrival: self.last_arrival.format(output::TIME_FORMAT).to_string(),
+            service_names: self.service_names.join(";"),
+        }
+    }
+}
+
+/// A static GTFS feed, indexed for answering questions about the schedule.
+pub struct Data {
+    pub routes: HashMap<String, Route>,             // by route_id
+    pub trips: HashMap<String, Trip>,               // by trip_id
+    pub stops: HashMap<String, Stop>,               // by stop_id
+    pub calendar: HashMap<String, Calendar>,        // by service_id
+    pub stop_times: HashMap<String, Vec<StopTime>>, // by stop_id
+    /// The stops of each trip in `stop_sequence` order, as `(stop_id, index)` pointing into
+    /// `stop_times`. Use [`Data::trip_stop_times`] to walk them.
+    pub stop_times_by_trip: HashMap<String, Vec<(String, usize)>>, // by trip_id
+    pub transfers: HashMap<String, Vec<Transfer>>,  // by from_stop_id
+    /// How far and fast riders walk between stops, for [`Data::footpaths`].
+    pub walking: Walking,
+    /// The IDs of the stops where buses stop, by location.
+    pub stop_grid: GridIndex<String>,
+    /// The zone of the agency's times, from `agency.txt`. Times in the feed, and the times
+    /// [`Data`] takes and gives, are all in this zone.
+    pub timezone: Tz,
+}
+
+impl Data {
+    /// Read the GTFS feed at `data`, which may be a directory or a `.zip` archive.
+    pub fn read(data: &str) -> Result<Self, anyhow::Error> {
+        let mut source = GtfsSource::open(data)?;
+
+        // calendar.txt is optional if every service is given by dates in calendar_dates.txt.
+        let mut calendar: HashMap<String, Calendar> = if source.has("calendar.txt") {
+            source
+                .reader("calendar.txt")?
+                .deserialize()
+                .map(|r| {
+                    let calendar = Calendar::from_calendar(r?)?;
+                    Ok((calendar.service_id.clone(), calendar))
+                })
+                .collect::<Result<_, anyhow::Error>>()
+                .context("Unable to read calendar.txt")?
+        } else {
+            HashMap::new()
+        };
+
+        if source.has("calendar_dates.txt") {
+            for exception in source.reader("calendar_dates.txt")?.deserialize() {
+                let exception: CalendarDateRaw =
+                    exception.context("Unable to read calendar_dates.txt")?;
+                let exception = CalendarDate::from_raw(exception)
+                    .context("Unable to read calendar_dates.txt")?;
+                calendar
+                    .entry(exception.service_id.clone())
+                    .or_insert_with(|| {
+                        Calendar::from_exceptions_only(exception.service_id.clone(), exception.date)
+                    })
+                    .exceptions
+                    .push(exception);
+            }
+        }
+
+        // Older feeds only name routes through trips.txt.
+        let routes: HashMap<String, Route> = if source.has("routes.txt") {
+            source
+                .reader("routes.txt")?
+                .deserialize()
+                .map(|r| r.map(Route::from_raw))
+                .map(|route| route.map(|route| (route.route_id.clone(), route)))
+                .collect::<Result<_, _>>()
+                .context("Unable to read routes.txt")?
+        } else {
+            HashMap::new()
+        };
+
+        let mut trips: HashMap<String, Trip> = source
+            .reader("trips.txt")?
+            .deserialize()
+            .map(|r| r.map(|trip: Trip| (trip.trip_id.clone(), trip)))
+            .collect::<Result<_, _>>()
+            .context("Unable to read trips.txt")?;
+        // Trips that never run, and stop times of trips that don't exist, are left out.
+        trips.retain(|_, trip| calendar.contains_key(&trip.service_id));
+
+        // Only some feeds name the route in trips.txt too.
+        for trip in trips
+            .values_mut()
+            .filter(|trip| trip.route_short_name.is_empty())
+        {
+            if let Some(route) = routes.get(&trip.route_id) {
+                trip.route_short_name = route.name().to_owned();
+            }
+        }
+
+        let mut stop_times = HashMap::new();
+
+        for stop_time in source.reader("stop_times.txt")?.deserialize() {
+            let stop_time = StopTime::from_raw(stop_time.context("Unable to read stop_times.txt")?);
+            if !trips.contains_key(&stop_time.trip_id) {
+                continue;
+            }
+            stop_times
+                .entry(stop_time.stop_id.clone())
+                .or_insert(vec![])
+                .push(stop_time);
+        }
+
+        let mut stop_times_by_trip: HashMap<String, Vec<(String, usize)>> = HashMap::new();
+        for (stop_id, times) in stop_times.iter() {
+            for (i, stop_time) in times.iter().enumerate() {
+                stop_times_by_trip
+                    .entry(stop_time.trip_id.clone())
+                    .or_default()
+                    .push((stop_id.clone(), i));
+            }
+        }
+        for trip_stops in stop_times_by_trip.values_mut() {
+            trip_stops.sort_by_key(|(stop_id, i)| stop_times[stop_id][*i].sequence());
+        }
+
+        let stops: HashMap<String, Stop> = source
+            .reader("stops.txt")?
+            .deserialize()
+            .map(|r| r.map(Stop::from_raw))
+            .map(|stop| stop.map(|stop| (stop.stop_id.clone(), stop)))
+            .collect::<Result<_, _>>()
+            .context("Unable to read stops.txt")?;
+
+        let mut stop_grid = GridIndex::default();
+        for stop in stops.values().filter(|stop: &&Stop| stop.is_stop()) {
+            if let Some(location) = stop.location() {
+                stop_grid.insert(location, stop.stop_id.clone());
+            }
+        }
+
+        let mut transfers: HashMap<String, Vec<Transfer>> = HashMap::new();
+        if source.has("transfers.txt") {
+            for transfer in source.reader("transfers.txt")?.deserialize() {
+                let transfer =
+                    Transfer::from_raw(transfer.context("Unable to read transfers.txt")?);
+                transfers
+                    .entry(transfer.from_stop_id.clone())
+                    .or_default()
+                    .push(transfer);
+            }
+        }
+
+        // All of a feed's agencies must share a zone. Without one, guess that the agency is
+        // where the user is.
+        let agency: Option<Agency> = if source.has("agency.txt") {
+            source
+                .reader("agency.txt")?
+                .deserialize()
+                .next()
+                .transpose()
+                .context("Unable to read agency.txt")?
+        } else {
+            None
+        };
+        let timezone = match agency {
+            Some(agency) => agency.agency_timezone.parse().map_err(|_| {
+                anyhow!(
+                    "Unknown time zone in agency.txt: {}",
+                    agency.agency_timezone
+                )
+            })?,
+            None => when::local_timezone(),
+        };
+
+        Ok(Self {
+            routes,
+            trips,
+            stops,
+            stop_times,
+            stop_times_by_trip,
+            calendar,
+            transfers,
+            walking: Walking::default(),
+            stop_grid,
+            timezone,
+        })
+    }
+
+    /// The time now, in the agency's time zone.
+    pub fn now(&self) -> NaiveDateTime {
+        self.local_time(Utc::now())
+    }
+
+    /// The given moment in the agency's time zone.
+    pub fn local_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        time.with_timezone(&self.timezone).naive_local()
+    }
+
+    /// Convert a time in the agency's zone to the same moment in another zone, for display.
+    pub fn in_timezone(&self, time: NaiveDateTime, timezone: Tz) -> NaiveDateTime {
+        self.timezone
+            .from_local_datetime(&time)
+            .earliest()
+            .map_or(time, |time| time.with_timezone(&timezone).naive_local())
+    }
+
+    /// The trip with the given id and the service it runs on.
+    pub(crate) fn trip_service(&self, trip_id: &str) -> Option<(&Trip, &Calendar)> {
+        let trip = self.trips.get(trip_id)?;
+        Some((trip, self.calendar.get(&trip.service_id)?))
+    }
+
+    /// The stops of the given trip, in `stop_sequence` order.
+    pub fn trip_stop_times<'d>(&'d self, trip_id: &str) -> impl Iterator<Item = &'d StopTime> {
+        self.stop_times_by_trip
+            .get(trip_id)
+            .into_iter()
+            .flatten()
+            .map(move |(stop_id, i)| &self.stop_times[stop_id][*i])
+    }
+
+    /// The real-time delay of a trip at one of its stops, as [`RealTimeInfo::delay`] gives it.
+    /// When the feed leaves out stop_sequence, the delay carries forward along the trip's stops
+    /// in the schedule instead.
+    pub fn real_time_delay(
+        &self,
+        real_time: &RealTimeInfo,
+        trip_id: &str,
+        stop_id: &str,
+        stop_sequence: Option<u32>,
+    ) -> Option<f64> {
+        real_time
+            .delay(trip_id, stop_id, stop_sequence)
+            .or_else(|| {
+                let updates = real_time.unsequenced.get(trip_id)?;
+                let stop_sequence = stop_sequence?;
+                self.trip_stop_times(trip_id)
+                    .take_while(|stop_time| stop_time.sequence() <= stop_sequence)
+                    .filter_map(|stop_time| {
+                        updates
+                            .iter()
+                            .find(|(stop_id, _)| *stop_id == stop_time.stop_id)
+                    })
+                    .last()
+                    .and_then(|(_, delay)| *delay)
+            })
+    }
+
+    /// The stops of the station the given stop is in, or of the station itself, in ID order. A
+    /// stop outside of any station is alone in its group.
+    pub fn station_stops<'d>(&'d self, stop: &'d Stop) -> Vec<&'d Stop> {
+        let station = if stop.is_station() {
+            &stop.stop_id
+        } else if !stop.parent_station.is_empty() {
+            &stop.parent_station
+        } else {
+            return vec![stop];
+        };
+
+        let mut stops: Vec<_> = self
+            .stops
+            .values()
+            .filter(|child| child.is_stop() && &child.parent_station == station)
+            .collect();
+        stops.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+        stops
+    }
+
+    /// Whether a rider in a wheelchair can board at the stop. Stops in a station that don't say
+    /// take after the station.
+    pub fn wheelchair_boarding(&self, stop: &Stop) -> Accessibility {
+        match Accessibility::from_code(&stop.wheelchair_boarding) {
+            Accessibility::Unknown => self
+                .stops
+                .get(&stop.parent_station)
+                .map_or(Accessibility::Unknown, |station| {
+                    Accessibility::from_code(&station.wheelchair_boarding)
+                }),
+            boarding => boarding,
+        }
+    }
+
+    /// Get buses at the stop matching the given filter and the real-time delay info.
+    pub fn stop_sched(
+        &self,
+        conf: FilterConfig,
+        real_time: &RealTimeInfo,
+    ) -> Result<StopBusInfo, anyhow::Error> {
+        let stop = match self.stops.get(conf.stop_id) {
+            Some(stop) => stop,
+            None => bail!("No such bus stop"),
+        };
+
+        // Which stops to list, and what to call them.
+        let (stop, mut stops) = if conf.station {
+            let station = self.stops.get(&stop.parent_station).unwrap_or(stop);
+            (station, self.station_stops(stop))
+        } else {
+            (stop, vec![stop])
+        };
+        if conf.nearby {
+            let near: Vec<_> = stops
+                .iter()
+                .filter_map(|stop| stop.location())
+                .flat_map(|location| self.stop_grid.within(location, self.walking.radius))
+                .map(|(stop_id, _)| &self.stops[stop_id])
+                .collect();
+            for near in near {
+                if !stops.iter().any(|stop| stop.stop_id == near.stop_id) {
+                    stops.push(near);
+                }
+            }
+        }
+
+        // Service days run past midnight, so the next buses may belong to yesterday's service
+        // or, late at night, to tomorrow's. Look at the next 24 hours across all three.
+        let after = conf.after.unwrap_or_else(|| self.now());
+        let today = after.date();
+        let until = after + chrono::Duration::days(1);
+        let service_days: Vec<_> = (-1..=1)
+            .map(|offset| today + chrono::Duration::days(offset))
+            .collect();
+
+        let mut buses = vec![];
+        for at in stops.iter() {
+            if conf.wheelchair && self.wheelchair_boarding(at) == Accessibility::No {
+                continue;
+            }
+
+            let stop_times = self
+                .stop_times
+                .get(&at.stop_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for bus in stop_times {
+                let (trip, service) = match self.trip_service(&bus.trip_id) {
+                    Some(found) => found,
+                    None => continue,
+                };
+
+                // Filter routes.
+                if !conf.matches_route(&trip.route_short_name) {
+                    continue;
+                }
+
+                // Filter out buses riders can't take, if asked. Where the feed doesn't say,
+                // assume they can't.
+                if conf.wheelchair && trip.wheelchair() != Accessibility::Yes
+                    || conf.bikes && trip.bikes() != Accessibility::Yes
+                {
+                    continue;
+                }
+
+                // Check which of the service days the service runs on.
+                let days: Vec<_> = service_days
+                    .iter()
+                    .copied()
+                    .filter(|&day| service.runs_on(day))
+                    .collect();
+                if days.is_empty() {
+                    continue;
+                }
+
+                // Filter out buses that can't be boarded here when listing departures, and ones
+                // that can't be left when listing arrivals. GTFS `1` means no pickup or no drop
+                // off.
+                let sequence = bus.sequence();
+                let trip_stops = self
+                    .stop_times_by_trip
+                    .get(&bus.trip_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let sequence_of =
+                    |(stop_id, i): &(String, usize)| self.stop_times[stop_id][*i].sequence();
+                let first_stop = trip_stops.first().map(sequence_of) == Some(sequence);
+                let last_stop = trip_stops.last().map(sequence_of) == Some(sequence);
+                if conf.arrivals && (bus.drop_off_type == "1" || first_stop)
+                    || !conf.arrivals && (bus.pickup_type == "1" || last_stop)
+                {
+                    continue;
+                }
+                let time = match if conf.arrivals {
+                    bus.arrival_time
+                } else {
+                    bus.departure_time
+                } {
+                    Some(time) => time,
+                    None => continue,
+                };
+
+                // Check for real-time delays.
+                let delay = self.real_time_delay(
+                    real_time,
+                    &bus.trip_id,
+                    &at.stop_id,
+                    bus.stop_sequence.parse().ok(),
+                );
+
+                let status = if real_time.cancelled.contains(&bus.trip_id) {
+                    BusStatus::Cancelled
+                } else if real_time
+                    .skipped
+                    .contains(&(bus.trip_id.clone(), at.stop_id.clone()))
+                {
+                    BusStatus::Skipped
+                } else {
+                    BusStatus::Scheduled
+                };
+
+                for day in days {
+                    let info = BusInfo {
+                        stop_id: at.stop_id.clone(),
+                        platform: at.platform().to_owned(),
+                        route: trip.route_short_name.clone(),
+                        headsign: trip.trip_headsign.clone(),
+                        trip_id: bus.trip_id.clone(),
+                        departure: time.on(day),
+                        delay,
+                        status,
+                        last_stop,
+                        wheelchair: trip.wheelchair(),
+                        bikes: trip.bikes(),
+                    };
+
+                    // Filter out buses that already came. A late bus may still be coming after
+                    // its scheduled time, and an early one may have gone before it.
+                    let predicted = info.predicted_departure();
+                    if after <= predicted && predicted < until {
+                        buses.push(info);
+                    }
+                }
+            }
+
+            // Add extra trips that only exist in the real-time data.
+            for added in real_time.added.get(&at.stop_id).into_iter().flatten() {
+                let departure = self.local_time(added.departure);
+                if self.trips.contains_key(&added.trip_id)
+                    || departure < after
+                    || departure >= until
+                {
+                    continue;
+                }
+
+                // Feeds without routes.txt only name routes through their trips.
+                let route = added
+                    .route_id
+                    .as_ref()
+                    .and_then(|route_id| {
+                        self.routes
+                            .get(route_id)
+                            .map(|route| route.name())
+                            .or_else(|| {
+                                self.trips
+                                    .values()
+                                    .find(|trip| &trip.route_id == route_id)
+                                    .map(|trip| trip.route_short_name.as_str())
+                            })
+                    })
+                    .unwrap_or_default()
+                    .to_owned();
+                if !conf.matches_route(&route) {
+                    continue;
+                }
+                // The feed says nothing about what these buses can take.
+                if conf.wheelchair || conf.bikes {
+                    continue;
+                }
+
+                buses.push(BusInfo {
+                    stop_id: at.stop_id.clone(),
+                    platform: at.platform().to_owned(),
+                    route,
+                    headsign: String::new(),
+                    trip_id: added.trip_id.clone(),
+                    departure,
+                    delay: None,
+                    status: BusStatus::Added,
+                    last_stop: false,
+                    wheelchair: Accessibility::Unknown,
+                    bikes: Accessibility::Unknown,
+                });
+            }
+        }
+
+        buses.sort_by_key(BusInfo::predicted_departure);
+
+        if let Some(len) = conf.how_many {
+            buses.truncate(len);
+        }
+
+        Ok(StopBusInfo {
+            stop_id: stop.stop_id.clone(),
+            stop_name: stop.stop_name.clone(),
+            stop_ids: stops.iter().map(|stop| stop.stop_id.clone()).collect(),
+            wheelchair_boarding: self.wheelchair_boarding(stop),
+            buses,
+        })
+    }
+
+    /// Find the stops within `radius` meters of a place, nearest first.
+    pub fn near(&self, place: LatLon, radius: f64) -> Vec<NearbyStop<'_>> {
+        self.stop_grid
+            .within(place, radius)
+            .into_iter()
+            .map(|(stop_id, distance)| {
+                let stop = &self.stops[stop_id];
+                NearbyStop {
+                    stop,
+                    distance,
+                    bearing: place.bearing_to(stop.location().unwrap()),
+                }
+            })
+            .collect()
+    }
+
+    /// List the routes that run on the given service day, with the span of their service.
+    pub fn routes_on(&self, day: NaiveDate) -> Vec<RouteSummary<'_>> {
+        let services: HashMap<&str, &Calendar> = self
+            .calendar
+            .values()
+            .filter(|service| service.runs_on(day))
+            .map(|service| (service.service_id.as_str(), service))
+            .collect();
+
+        let mut routes: HashMap<&str, RouteSummary> = HashMap::new();
+        for stop_time in self.stop_times.values().flatten() {
+            let trip = match self.trips.get(&stop_time.trip_id) {
+                Some(trip) => trip,
+                None => continue,
+            };
+            let service = match services.get(trip.service_id.as_str()) {
+                Some(service) => service,
+                None => continue,
+            };
+            let (departure, arrival) = match (stop_time.departure_time, stop_time.arrival_time) {
+                (Some(departure), Some(arrival)) => (departure.on(day), arrival.on(day)),
+                _ => continue,
+            };
+
+            let summary = routes
+                .entry(&trip.route_id)
+                .or_insert_with(|| RouteSummary {
+                    route_id: trip.route_id.clone(),
+                    route_short_name: trip.route_short_name.clone(),
+                    route: self.routes.get(&trip.route_id),
+                    first_departure: departure,
+                    last_arrival: arrival,
+                    service_names: vec![],
+                });
+            summary.first_departure = summary.first_departure.min(departure);
+            summary.last_arrival = summary.last_arrival.max(arrival);
+            if !service.service_name.is_empty()
+                && !summary.service_names.contains(&service.service_name)
+            {
+                summary.service_names.push(service.service_name.clone());
+            }
+        }
+
+        // Use the feed's order if it has one, and otherwise put numbered routes in numeric order.
+        let mut routes: Vec<_> = routes.into_values().collect();
+        routes.sort_by_key(|summary| {
+            (
+                summary.route.and_then(|route| route.route_sort_order),
+                summary.route_short_name.parse::<u32>().unwrap_or(u32::MAX),
+                summary.route_short_name.clone(),
+            )
+        });
+        for summary in routes.iter_mut() {
+            summary.service_names.sort();
+        }
+
+        routes
+    }
+}
@@ -0,0 +1,290 @@
+//! Finding stops by name, forgiving typos and the many ways of writing a street name.
+
+use std::collections::HashSet;
+
+use crate::schedule::Data;
+
+/// How well a stop must match to be listed, from 0 to 1.
+const MIN_SCORE: f64 = 0.6;
+
+/// Street words and their abbreviations, written the one way so that either matches.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("st", "street"),
+    ("str", "street"),
+    ("ave", "avenue"),
+    ("av", "avenue"),
+    ("blvd", "boulevard"),
+    ("rd", "road"),
+    ("dr", "drive"),
+    ("ln", "lane"),
+    ("ct", "court"),
+    ("pl", "place"),
+    ("pkwy", "parkway"),
+    ("hwy", "highway"),
+    ("sq", "square"),
+    ("ctr", "center"),
+    ("cntr", "center"),
+    ("univ", "university"),
+    ("n", "north"),
+    ("s", "south"),
+    ("e", "east"),
+    ("w", "west"),
+];
+
+/// Words that only join the others, as in "University & Park" or "Park at University".
+const CONNECTORS: &[&str] = &["and", "at", "of", "on", "the"];
+
+/// Split text into lowercase words, spelling out abbreviations and dropping connecting words.
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !CONNECTORS.contains(word))
+        .map(|word| {
+            ABBREVIATIONS
+                .iter()
+                .find(|(short, _)| *short == word)
+                .map_or(word, |(_, long)| long)
+                .to_owned()
+        })
+        .collect()
+}
+
+/// The number of single-character edits (insertions, deletions, substitutions and swaps of
+/// neighbours) to turn one word into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<_>, Vec<_>) = (a.chars().collect(), b.chars().collect());
+
+    // d[i][j] is the distance between the first i letters of a and the first j letters of b.
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// How well a query word matches a word of a stop, from 0 (not at all) to 1 (exactly).
+fn similarity(query: &str, word: &str) -> f64 {
+    if query == word {
+        return 1.0;
+    }
+    let len = query.chars().count();
+    if len >= 2 && word.starts_with(query) {
+        return 0.9;
+    }
+    // Numbers, like stop codes and addresses, are either right or wrong.
+    if query.chars().all(|c| c.is_ascii_digit()) {
+        return 0.0;
+    }
+    if len >= 3 && word.contains(query) {
+        return 0.7;
+    }
+
+    // Allow more typos in longer words.
+    let allowed = match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    let distance = edit_distance(query, word);
+    if distance <= allowed {
+        0.85 - 0.1 * distance as f64
+    } else {
+        0.0
+    }
+}
+
+impl Data {
+    /// Find the stops matching the given strings, best first. Returns `(stop_id, stop_name)`
+    /// pairs.
+    ///
+    /// Stops match on their name, street and cross street, tolerating typos and abbreviations
+    /// like "Univ & Randal", or exactly on their stop code or ID.
+    pub fn search(&self, string: Vec<&str>) -> Vec<(String, String)> {
+        let query = string.join(" ");
+        let query_words = words(&query);
+        let exact = query.trim().to_lowercase();
+
+        let mut stops: Vec<(f64, String, String)> = self
+            .stops
+            .values()
+            .filter_map(|stop| {
+                if stop.stop_code.to_lowercase() == exact || stop.stop_id.to_lowercase() == exact {
+                    return Some((2.0, stop.stop_name.clone(), stop.stop_id.clone()));
+                }
+                if query_words.is_empty() {
+                    return None;
+                }
+
+                let name_words = words(&stop.stop_name);
+                let other_words = words(&stop.primary_street)
+                    .into_iter()
+                    .chain(words(&stop.cross_location))
+                    .chain(std::iter::once(stop.stop_code.to_lowercase()));
+                let stop_words: HashSet<String> =
+                    name_words.iter().cloned().chain(other_words).collect();
+
+                // How well the query is covered by the stop, word by word.
+                let query_score = query_words
+                    .iter()
+                    .map(|query| {
+                        stop_words
+                            .iter()
+                            .map(|word| similarity(query, word))
+                            .fold(0.0, f64::max)
+                    })
+                    .sum::<f64>()
+                    / query_words.len() as f64;
+                if query_score < MIN_SCORE {
+                    return None;
+                }
+
+                // Prefer stops whose names have little besides what was asked for.
+                let covered = name_words
+                    .iter()
+                    .filter(|word| {
+                        query_words
+                            .iter()
+                            .any(|query| similarity(query, word) > 0.0)
+                    })
+                    .count();
+                let name_score = covered as f64 / name_words.len().max(1) as f64;
+
+                Some((
+                    0.8 * query_score + 0.2 * name_score,
+                    stop.stop_name.clone(),
+                    stop.stop_id.clone(),
+                ))
+            })
+            .collect();
+
+        stops.sort_by(|(a_score, a_name, a_id), (b_score, b_name, b_id)| {
+            b_score
+                .partial_cmp(a_score)
+                .unwrap()
+                .then_with(|| a_name.cmp(b_name))
+                .then_with(|| a_id.cmp(b_id))
+        });
+
+        stops
+            .into_iter()
+            .map(|(_, stop_name, stop_id)| (stop_id, stop_name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gtfs::{Stop, StopRaw};
+
+    /// The minimal test feed, with its stops replaced by the given `stops.txt`.
+    fn data(stops: &str) -> Data {
+        let mut data = Data::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/gtfs-minimal"
+        ))
+        .unwrap();
+        data.stops = csv::Reader::from_reader(stops.as_bytes())
+            .deserialize()
+            .map(|raw: Result<StopRaw, _>| {
+                let stop = Stop::from_raw(raw.unwrap());
+                (stop.stop_id.clone(), stop)
+            })
+            .collect();
+        data
+    }
+
+    fn madison() -> Data {
+        data(
+            "stop_id,stop_code,stop_name\n\
+             1,1001,University Ave & Randall Ave\n\
+             2,1002,University Ave & Park St\n\
+             3,1003,Randall Ave & Regent St\n\
+             4,1004,Randall Ave\n",
+        )
+    }
+
+    fn stop_ids(found: Vec<(String, String)>) -> Vec<String> {
+        found.into_iter().map(|(stop_id, _)| stop_id).collect()
+    }
+
+    #[test]
+    fn words_spell_out_abbreviations() {
+        assert_eq!(words("Univ & Randal"), ["university", "randal"]);
+        assert_eq!(
+            words("university ave at randall"),
+            ["university", "avenue", "randall"]
+        );
+        assert_eq!(words("N. Park St."), ["north", "park", "street"]);
+        assert!(words(" & at the ").is_empty());
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("randall", "randall"), 0);
+        assert_eq!(edit_distance("randal", "randall"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "park"), 4);
+        // Swapping neighbouring letters is one edit, not two.
+        assert_eq!(edit_distance("univeristy", "university"), 1);
+    }
+
+    #[test]
+    fn similarities() {
+        assert_eq!(similarity("park", "park"), 1.0);
+        assert_eq!(similarity("rand", "randall"), 0.9);
+        assert_eq!(similarity("gent", "regent"), 0.7);
+        assert_eq!(similarity("univeristy", "university"), 0.75);
+        assert_eq!(similarity("prk", "park"), 0.75);
+        assert_eq!(similarity("pk", "park"), 0.0);
+        assert_eq!(similarity("universe", "park"), 0.0);
+    }
+
+    #[test]
+    fn digits_must_match() {
+        assert_eq!(similarity("1004", "1004"), 1.0);
+        assert_eq!(similarity("1004", "1005"), 0.0);
+        assert_eq!(similarity("004", "1004"), 0.0);
+
+        let data = madison();
+        assert_eq!(stop_ids(data.search(vec!["1002"])), ["2"]);
+        assert!(data.search(vec!["1005"]).is_empty());
+    }
+
+    #[test]
+    fn search_forgives_typos_and_abbreviations() {
+        let data = madison();
+        for query in ["Univ & Randal", "university ave at randall"] {
+            let found = data.search(vec![query]);
+            assert_eq!(found[0].0, "1", "{}", query);
+            assert_eq!(found[0].1, "University Ave & Randall Ave");
+        }
+        assert_eq!(stop_ids(data.search(vec!["Univeristy", "Park"]))[0], "2");
+    }
+
+    #[test]
+    fn search_order() {
+        let data = madison();
+        // Stops with little besides what was asked for come first, then by name.
+        assert_eq!(stop_ids(data.search(vec!["randall"])), ["4", "3", "1"]);
+        // A stop ID matches only that stop, not the codes it is part of.
+        assert_eq!(stop_ids(data.search(vec!["3"])), ["3"]);
+        assert!(data.search(vec!["Mineral Point"]).is_empty());
+    }
+}
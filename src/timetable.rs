@@ -0,0 +1,182 @@
+//! Printed-schedule style timetables: the timepoint stops of a route as columns and the day's trips
+//! as rows.
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::gtfs::StopTime;
+use crate::output::{self, TimetableRecord};
+use crate::schedule::{same_route, Data};
+
+/// One trip in a [`Timetable`].
+pub struct TimetableRow {
+    pub trip_id: String,
+    pub headsign: String,
+    /// When the trip leaves each of the timetable's stops, if it stops there.
+    pub times: Vec<Option<NaiveDateTime>>,
+}
+
+/// The timetable of one direction of a route on some day, from [`Data::timetable`].
+pub struct Timetable {
+    pub route_short_name: String,
+    pub direction_id: String,
+    pub direction_name: String,
+    /// The timepoint stops, as `(stop_id, stop_name)`, in the order buses visit them.
+    pub stops: Vec<(String, String)>,
+    /// The trips, in order of departure.
+    pub trips: Vec<TimetableRow>,
+}
+
+impl Timetable {
+    /// Flatten into one record per trip and stop, for `--format`.
+    pub fn records(&self) -> Vec<TimetableRecord<'_>> {
+        self.trips
+            .iter()
+            .flat_map(|trip| {
+                self.stops.iter().zip(trip.times.iter()).filter_map(
+                    move |((stop_id, stop_name), time)| {
+                        Some(TimetableRecord {
+                            route_short_name: &self.route_short_name,
+                            direction_id: &self.direction_id,
+                            trip_id: &trip.trip_id,
+                            headsign: &trip.headsign,
+                            stop_id,
+                            stop_name,
+                            departure_time: time.as_ref()?.format(output::TIME_FORMAT).to_string(),
+                        })
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl Data {
+    /// Build the timetable of the given route on the given service day, one per direction. If a
+    /// direction is given, only that direction is included.
+    pub fn timetable(
+        &self,
+        route: &str,
+        direction_id: Option<&str>,
+        day: NaiveDate,
+    ) -> Vec<Timetable> {
+        // The timepoints of each trip running that day, by direction. Trips are kept in trip_id
+        // order, so that ties come out the same way every time.
+        let mut by_direction: BTreeMap<&str, BTreeMap<&str, Vec<&StopTime>>> = BTreeMap::new();
+        for stop_time in self.stop_times.values().flatten() {
+            let (trip, service) = match self.trip_service(&stop_time.trip_id) {
+                Some(found) => found,
+                None => continue,
+            };
+
+            // GTFS treats stops with times as exact unless they say otherwise.
+            if !same_route(&trip.route_short_name, route)
+                || direction_id.is_some_and(|direction_id| trip.direction_id != direction_id)
+                || !service.runs_on(day)
+                || stop_time.timepoint == "0"
+                || stop_time.departure_time.is_none()
+            {
+                continue;
+            }
+
+            by_direction
+                .entry(&trip.direction_id)
+                .or_default()
+                .entry(&trip.trip_id)
+                .or_default()
+                .push(stop_time);
+        }
+
+        by_direction
+            .into_iter()
+            .map(|(direction_id, mut trips)| {
+                for stop_times in trips.values_mut() {
+                    stop_times.sort_by_key(|stop_time| stop_time.sequence());
+                }
+
+                let columns = merge_stop_orders(&trips);
+
+                let mut rows: Vec<_> = trips
+                    .iter()
+                    .map(|(trip_id, stop_times)| {
+                        let trip = &self.trips[*trip_id];
+
+                        // Walk the trip and the columns together, so that a stop visited twice
+                        // (e.g. on a loop) fills the right column each time.
+                        let mut times = vec![None; columns.len()];
+                        let mut column = 0;
+                        for stop_time in stop_times.iter() {
+                            if let Some(offset) = columns[column..]
+                                .iter()
+                                .position(|stop_id| *stop_id == stop_time.stop_id)
+                            {
+                                column += offset;
+                                times[column] = stop_time.departure_time.map(|time| time.on(day));
+                                column += 1;
+                            }
+                        }
+
+                        TimetableRow {
+                            trip_id: trip.trip_id.clone(),
+                            headsign: trip.trip_headsign.clone(),
+                            times,
+                        }
+                    })
+                    .collect();
+                rows.sort_by(|a, b| {
+                    let first = |row: &TimetableRow| row.times.iter().flatten().next().cloned();
+                    first(a)
+                        .cmp(&first(b))
+                        .then_with(|| a.trip_id.cmp(&b.trip_id))
+                });
+
+                let some_trip = &self.trips[*trips.keys().next().unwrap()];
+                Timetable {
+                    route_short_name: some_trip.route_short_name.clone(),
+                    direction_id: direction_id.to_owned(),
+                    direction_name: some_trip.trip_direction_name.clone(),
+                    stops: columns
+                        .iter()
+                        .map(|stop_id| {
+                            let stop_name = self
+                                .stops
+                                .get(*stop_id)
+                                .map(|stop| stop.stop_name.clone())
+                                .unwrap_or_default();
+                            (stop_id.to_string(), stop_name)
+                        })
+                        .collect(),
+                    trips: rows,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Work out one order of stops that fits every trip. Trips on the same route may skip stops or
+/// start part way along, so start from the longest and slot the other trips' stops in around the
+/// stops they share with it.
+fn merge_stop_orders<'a>(trips: &BTreeMap<&str, Vec<&'a StopTime>>) -> Vec<&'a str> {
+    let mut trips: Vec<_> = trips.values().collect();
+    // Stable, so ties stay in trip_id order.
+    trips.sort_by_key(|stop_times| std::cmp::Reverse(stop_times.len()));
+
+    let mut columns: Vec<&str> = vec![];
+    for stop_times in trips {
+        let mut column = 0;
+        for stop_time in stop_times.iter() {
+            if let Some(offset) = columns[column..]
+                .iter()
+                .position(|stop_id| *stop_id == stop_time.stop_id)
+            {
+                column += offset + 1;
+            } else {
+                columns.insert(column, &stop_time.stop_id);
+                column += 1;
+            }
+        }
+    }
+
+    columns
+}
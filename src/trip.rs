@@ -0,0 +1,166 @@
+//! Following one trip along its route: every stop it makes, when, and how far along it is.
+
+use anyhow::bail;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::output::{self, TripStopRecord};
+use crate::realtime::RealTimeInfo;
+use crate::schedule::{BusStatus, Data};
+
+/// One stop of a trip, from [`Data::trip_sched`].
+pub struct TripStop {
+    pub stop_id: String,
+    pub stop_name: String,
+    pub stop_sequence: u32,
+    /// The scheduled departure time. Stops that are not timepoints may not have one.
+    pub departure: Option<NaiveDateTime>,
+    /// Real-time delay in seconds, if known.
+    pub delay: Option<f64>,
+    pub status: BusStatus,
+    /// Whether the bus has already left this stop.
+    pub passed: bool,
+}
+
+impl TripStop {
+    /// When the bus should actually leave, going by the real-time data.
+    pub fn predicted_departure(&self) -> Option<NaiveDateTime> {
+        self.departure.map(|departure| {
+            departure + chrono::Duration::seconds(self.delay.unwrap_or(0.0) as i64)
+        })
+    }
+}
+
+/// A trip and its stops, from [`Data::trip_sched`].
+pub struct TripInfo {
+    pub trip_id: String,
+    /// The route's short name, e.g. `80`.
+    pub route: String,
+    pub headsign: String,
+    /// The service day the times are on.
+    pub service_day: NaiveDate,
+    pub stops: Vec<TripStop>,
+}
+
+impl TripInfo {
+    /// Flatten into one record per stop, for `--format`.
+    pub fn records(&self) -> Vec<TripStopRecord<'_>> {
+        let format = |time: Option<NaiveDateTime>| {
+            time.map(|time| time.format(output::TIME_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+
+        self.stops
+            .iter()
+            .map(|stop| TripStopRecord {
+                trip_id: &self.trip_id,
+                route: &self.route,
+                headsign: &self.headsign,
+                stop_sequence: stop.stop_sequence,
+                stop_id: &stop.stop_id,
+                stop_name: &stop.stop_name,
+                status: stop.status.name(),
+                scheduled_time: format(stop.departure),
+                delay_seconds: stop.delay.map(|delay| delay as i64),
+                predicted_time: format(stop.predicted_departure()),
+                passed: stop.passed,
+            })
+            .collect()
+    }
+}
+
+impl Data {
+    /// Follow the given trip along its route, with the real-time delay info. `now` decides which
+    /// service day's run of the trip to show and which stops it has passed: the run still under
+    /// way or next to start, going by yesterday's, today's and tomorrow's service.
+    pub fn trip_sched(
+        &self,
+        trip_id: &str,
+        now: NaiveDateTime,
+        real_time: &RealTimeInfo,
+    ) -> Result<TripInfo, anyhow::Error> {
+        let (trip, service) = match self.trip_service(trip_id) {
+            Some(found) => found,
+            None => bail!("No such trip"),
+        };
+
+        let cancelled = real_time.cancelled.contains(trip_id);
+        let stop_info = |day: NaiveDate| -> Vec<TripStop> {
+            self.trip_stop_times(trip_id)
+                .map(|stop_time| {
+                    let sequence = stop_time.sequence();
+                    let status = if cancelled {
+                        BusStatus::Cancelled
+                    } else if real_time
+                        .skipped
+                        .contains(&(trip_id.to_owned(), stop_time.stop_id.clone()))
+                    {
+                        BusStatus::Skipped
+                    } else {
+                        BusStatus::Scheduled
+                    };
+
+                    TripStop {
+                        stop_id: stop_time.stop_id.clone(),
+                        stop_name: self
+                            .stops
+                            .get(&stop_time.stop_id)
+                            .map(|stop| stop.stop_name.clone())
+                            .unwrap_or_default(),
+                        stop_sequence: sequence,
+                        departure: stop_time.departure_time.map(|time| time.on(day)),
+                        delay: self.real_time_delay(
+                            real_time,
+                            trip_id,
+                            &stop_time.stop_id,
+                            Some(sequence),
+                        ),
+                        status,
+                        passed: false,
+                    }
+                })
+                .collect()
+        };
+
+        // Prefer the run that has not finished yet. If none of the nearby days have one, show
+        // the trip on today's date anyway so its stops can still be seen.
+        let today = now.date();
+        let runs: Vec<_> = (-1..=1)
+            .map(|offset| today + chrono::Duration::days(offset))
+            .filter(|&day| service.runs_on(day))
+            .map(|day| (day, stop_info(day)))
+            .collect();
+        let unfinished = runs.iter().position(|(_, stops)| {
+            stops
+                .iter()
+                .filter_map(TripStop::predicted_departure)
+                .next_back()
+                .is_some_and(|last| last >= now)
+        });
+        let (service_day, mut stops) = match unfinished {
+            Some(i) => runs.into_iter().nth(i).unwrap(),
+            None => match runs.into_iter().last() {
+                Some(run) => run,
+                None => (today, stop_info(today)),
+            },
+        };
+
+        // Stops without times are passed once a later stop is.
+        if let Some(last_passed) = stops.iter().rposition(|stop| {
+            stop.predicted_departure()
+                .is_some_and(|departure| departure < now)
+        }) {
+            for stop in stops[..=last_passed].iter_mut() {
+                stop.passed = true;
+            }
+        }
+
+        Ok(TripInfo {
+            trip_id: trip.trip_id.clone(),
+            route: trip.route_short_name.clone(),
+            headsign: trip.trip_headsign.clone(),
+            service_day,
+            stops,
+        })
+    }
+}
@@ -0,0 +1,644 @@
+//! The full-screen interface of `bus tui`: find a stop, see its buses, follow a trip and read a
+//! route's timetable, all from schedule data read once at the start.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use chrono_tz::Tz;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+
+use bus::config::Favorites;
+use bus::realtime::{self, RealTimeInfo};
+use bus::{BusStatus, Data, FilterConfig, StopBusInfo, Timetable, TripInfo};
+
+use crate::{print_countdown, print_status, shown, DEFAULT_WATCH_INTERVAL};
+
+/// How many stops to list when searching.
+const MAX_RESULTS: usize = 50;
+
+/// How many buses to list at a stop.
+const MAX_BUSES: usize = 30;
+
+/// How many rows Page Up and Page Down move by.
+const PAGE: usize = 10;
+
+/// A screen of the interface. Screens pile up as the user drills down, and Esc goes back.
+enum Screen {
+    /// Searching for a stop, or picking a favorite.
+    Search,
+    Stop {
+        stop_id: String,
+        info: StopBusInfo,
+    },
+    Trip {
+        trip_id: String,
+        info: TripInfo,
+    },
+    Timetable {
+        timetables: Vec<Timetable>,
+        /// Which direction is shown.
+        shown: usize,
+    },
+}
+
+/// The state of `bus tui`.
+pub struct App<'d> {
+    data: &'d Data,
+    /// The agency profile name, which the favorites are kept under.
+    agency: String,
+    trip_update_url: Option<String>,
+    tz: Option<Tz>,
+
+    real_time: RealTimeInfo,
+    /// When we last started fetching the real-time data.
+    fetched: Option<Instant>,
+    /// The fetch in progress, if any. Fetching happens off to the side so that the screen keeps
+    /// working while the feed is slow.
+    fetching: Option<Receiver<Result<RealTimeInfo, anyhow::Error>>>,
+    /// When the real-time data was made, or fetched if the feed doesn't say.
+    real_time_age: Option<DateTime<Utc>>,
+
+    favorites: Favorites,
+    query: String,
+    /// The stops matching the query, or the favorites if there is no query, as
+    /// `(stop_id, stop_name)`.
+    results: Vec<(String, String)>,
+
+    /// The screens, each with its selected row. The last one is showing.
+    screens: Vec<(Screen, usize)>,
+    /// Something to tell the user, like an error, until the next key.
+    message: Option<String>,
+}
+
+impl<'d> App<'d> {
+    pub fn new(
+        data: &'d Data,
+        agency: String,
+        trip_update_url: Option<String>,
+        tz: Option<Tz>,
+    ) -> Result<Self, anyhow::Error> {
+        let mut app = Self {
+            data,
+            agency,
+            trip_update_url,
+            tz,
+            real_time: RealTimeInfo::default(),
+            fetched: None,
+            fetching: None,
+            real_time_age: None,
+            favorites: Favorites::load()?,
+            query: String::new(),
+            results: vec![],
+            screens: vec![(Screen::Search, 0)],
+            message: None,
+        };
+        app.search();
+        Ok(app)
+    }
+
+    /// Take over the terminal until the user quits.
+    pub fn run(mut self) -> Result<(), anyhow::Error> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), anyhow::Error> {
+        loop {
+            let refresh = Duration::from_secs(DEFAULT_WATCH_INTERVAL);
+            if self
+                .fetched
+                .is_none_or(|fetched| fetched.elapsed() >= refresh)
+            {
+                self.refresh();
+            }
+            self.receive();
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Wake up every second to keep the countdowns current.
+            if event::poll(Duration::from_secs(1))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.handle(key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start fetching the real-time data again, unless a fetch is already going, and bring the
+    /// showing screen up to date in the meantime.
+    fn refresh(&mut self) {
+        self.fetched = Some(Instant::now());
+        if let (Some(url), None) = (&self.trip_update_url, &self.fetching) {
+            let (sender, receiver) = mpsc::channel();
+            let url = url.clone();
+            thread::spawn(move || {
+                // Nobody is listening any more if the user has quit.
+                let _ = sender.send(realtime::fetch_real_time_data(&url));
+            });
+            self.fetching = Some(receiver);
+        }
+        self.reload();
+    }
+
+    /// Take the real-time data from the fetch in progress, if it is done, and update the screen
+    /// with it.
+    fn receive(&mut self) {
+        let result = match self.fetching.as_ref().map(Receiver::try_recv) {
+            None | Some(Err(TryRecvError::Empty)) => return,
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Disconnected)) => {
+                Err(anyhow::anyhow!("Unable to fetch real-time data."))
+            }
+        };
+        self.fetching = None;
+        match result {
+            Ok(real_time) => {
+                self.real_time_age = real_time.timestamp.or_else(|| Some(Utc::now()));
+                self.real_time = real_time;
+            }
+            Err(e) => self.message = Some(format!("{:#}", e)),
+        }
+        self.reload();
+    }
+
+    /// Work out the showing screen again, e.g. with new real-time data.
+    fn reload(&mut self) {
+        let now = self.data.now();
+        let result = match self.screens.last_mut() {
+            Some((Screen::Stop { stop_id, info }, _)) => {
+                let filter = FilterConfig::new(stop_id).station().how_many(MAX_BUSES);
+                self.data
+                    .stop_sched(filter, &self.real_time)
+                    .map(|new| *info = new)
+            }
+            Some((Screen::Trip { trip_id, info }, _)) => self
+                .data
+                .trip_sched(trip_id, now, &self.real_time)
+                .map(|new| *info = new),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            self.message = Some(e.to_string());
+        }
+    }
+
+    fn search(&mut self) {
+        self.results = if self.query.trim().is_empty() {
+            self.favorites
+                .stops(&self.agency)
+                .iter()
+                .filter_map(|stop_id| self.data.stops.get(stop_id))
+                .map(|stop| (stop.stop_id.clone(), stop.stop_name.clone()))
+                .collect()
+        } else {
+            let mut results = self.data.search(self.query.split_whitespace().collect());
+            results.truncate(MAX_RESULTS);
+            results
+        };
+        if let Some((Screen::Search, selected)) = self.screens.last_mut() {
+            *selected = 0;
+        }
+    }
+
+    fn open_stop(&mut self, stop_id: &str) {
+        let filter = FilterConfig::new(stop_id).station().how_many(MAX_BUSES);
+        match self.data.stop_sched(filter, &self.real_time) {
+            Ok(info) => self.screens.push((
+                Screen::Stop {
+                    stop_id: stop_id.to_owned(),
+                    info,
+                },
+                0,
+            )),
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    fn open_trip(&mut self, trip_id: &str) {
+        match self
+            .data
+            .trip_sched(trip_id, self.data.now(), &self.real_time)
+        {
+            Ok(info) => {
+                // Start at the next stop the bus comes to.
+                let next = info.stops.iter().take_while(|stop| stop.passed).count();
+                let selected = next.min(info.stops.len().saturating_sub(1));
+                self.screens.push((
+                    Screen::Trip {
+                        trip_id: trip_id.to_owned(),
+                        info,
+                    },
+                    selected,
+                ));
+            }
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    /// Show the timetable of the trip's route, starting with the trip's direction.
+    fn open_timetable(&mut self, trip_id: &str) {
+        let trip = match self.data.trips.get(trip_id) {
+            Some(trip) => trip,
+            None => {
+                self.message = Some("No timetable for a trip added in real time".into());
+                return;
+            }
+        };
+        let now = self.data.now();
+        let timetables = self
+            .data
+            .timetable(&trip.route_short_name, None, now.date());
+        if timetables.is_empty() {
+            self.message = Some(format!(
+                "Route {} does not run today",
+                trip.route_short_name
+            ));
+            return;
+        }
+
+        let shown = timetables
+            .iter()
+            .position(|timetable| timetable.direction_id == trip.direction_id)
+            .unwrap_or(0);
+        let selected = next_trip(&timetables[shown], now);
+        self.screens
+            .push((Screen::Timetable { timetables, shown }, selected));
+    }
+
+    /// Act on a key. Returns false to quit.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        self.message = None;
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+
+        let rows = self.rows();
+        let (screen, selected) = self.screens.last_mut().unwrap();
+
+        // Moving around works the same everywhere.
+        let last = rows.saturating_sub(1);
+        match key.code {
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(last),
+            KeyCode::PageUp => *selected = selected.saturating_sub(PAGE),
+            KeyCode::PageDown => *selected = (*selected + PAGE).min(last),
+            KeyCode::Home => *selected = 0,
+            KeyCode::End => *selected = last,
+            _ => {}
+        }
+        let selected = *selected;
+
+        match screen {
+            Screen::Search => match key.code {
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.search();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.search();
+                }
+                KeyCode::Esc if self.query.is_empty() => return false,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.search();
+                }
+                KeyCode::Enter => {
+                    if let Some((stop_id, _)) = self.results.get(selected).cloned() {
+                        self.open_stop(&stop_id);
+                    }
+                }
+                _ => {}
+            },
+
+            Screen::Stop { stop_id, info } => {
+                let trip_id = info.buses.get(selected).map(|bus| bus.trip_id.clone());
+                match key.code {
+                    KeyCode::Enter => {
+                        if let Some(trip_id) = trip_id {
+                            self.open_trip(&trip_id);
+                        }
+                    }
+                    KeyCode::Char('t') => {
+                        if let Some(trip_id) = trip_id {
+                            self.open_timetable(&trip_id);
+                        }
+                    }
+                    KeyCode::Char('f') => {
+                        let stop_id = stop_id.clone();
+                        let starred = self.favorites.toggle(&self.agency, &stop_id);
+                        self.message = Some(match self.favorites.save() {
+                            Ok(()) if starred => "Added to favorites".into(),
+                            Ok(()) => "Removed from favorites".into(),
+                            Err(e) => format!("{:#}", e),
+                        });
+                        // The favorites are listed when not searching.
+                        if self.query.is_empty() {
+                            self.search();
+                        }
+                    }
+                    _ => return self.handle_common(key),
+                }
+            }
+
+            Screen::Trip { info, .. } => {
+                let stop_id = info.stops.get(selected).map(|stop| stop.stop_id.clone());
+                let trip_id = info.trip_id.clone();
+                match key.code {
+                    KeyCode::Enter => {
+                        if let Some(stop_id) = stop_id {
+                            self.open_stop(&stop_id);
+                        }
+                    }
+                    KeyCode::Char('t') => self.open_timetable(&trip_id),
+                    _ => return self.handle_common(key),
+                }
+            }
+
+            Screen::Timetable { timetables, shown } => match key.code {
+                KeyCode::Tab | KeyCode::Right | KeyCode::Left => {
+                    *shown = if key.code == KeyCode::Left {
+                        (*shown + timetables.len() - 1) % timetables.len()
+                    } else {
+                        (*shown + 1) % timetables.len()
+                    };
+                    let next = next_trip(&timetables[*shown], self.data.now());
+                    self.screens.last_mut().unwrap().1 = next;
+                }
+                _ => return self.handle_common(key),
+            },
+        }
+        true
+    }
+
+    /// The keys for going back, refreshing and quitting, outside of the search screen.
+    fn handle_common(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc | KeyCode::Backspace => {
+                self.screens.pop();
+                self.reload();
+            }
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Char('/') => {
+                self.screens.truncate(1);
+                self.query.clear();
+                self.search();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// How many rows the showing screen has to move between.
+    fn rows(&self) -> usize {
+        match &self.screens.last().unwrap().0 {
+            Screen::Search => self.results.len(),
+            Screen::Stop { info, .. } => info.buses.len(),
+            Screen::Trip { info, .. } => info.stops.len(),
+            Screen::Timetable { timetables, shown } => timetables[*shown].trips.len(),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [title, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let (screen, selected) = self.screens.last().unwrap();
+        let now = self.data.now();
+        let time = |time: NaiveDateTime| {
+            shown(self.data, self.tz, time)
+                .format("%l:%M %p")
+                .to_string()
+        };
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        let (heading, keys) = match screen {
+            Screen::Search => {
+                let items: Vec<_> = self
+                    .results
+                    .iter()
+                    .map(|(stop_id, stop_name)| {
+                        let star = if self.favorites.contains(&self.agency, stop_id) {
+                            "★"
+                        } else {
+                            " "
+                        };
+                        ListItem::new(format!("{} {:>6}  {}", star, stop_id, stop_name))
+                    })
+                    .collect();
+                if items.is_empty() && self.query.is_empty() {
+                    frame.render_widget(
+                        Paragraph::new(
+                            "Type to search for a stop by name, street or code. Stops starred \
+                             with f show up here.",
+                        ),
+                        body,
+                    );
+                } else {
+                    self.draw_list(frame, body, items, *selected);
+                }
+                (
+                    format!("Find a stop: {}▏", self.query),
+                    "type to search  ↑↓ move  enter open  esc clear/quit",
+                )
+            }
+
+            Screen::Stop { stop_id, info } => {
+                let platforms = info.stop_ids.len() > 1;
+                let items: Vec<_> = info
+                    .buses
+                    .iter()
+                    .map(|bus| {
+                        let countdown = match bus.status {
+                            BusStatus::Scheduled | BusStatus::Added => {
+                                print_countdown(bus.predicted_departure() - now)
+                            }
+                            _ => String::new(),
+                        };
+                        ListItem::new(format!(
+                            "{} {:10} {:10} {}{}  {}{}",
+                            time(bus.predicted_departure()),
+                            countdown,
+                            print_status(bus.status, bus.delay),
+                            if platforms {
+                                format!("{} ", bus.platform)
+                            } else {
+                                String::new()
+                            },
+                            bus.route,
+                            bus.headsign,
+                            if bus.last_stop { " (last stop)" } else { "" },
+                        ))
+                    })
+                    .collect();
+                if items.is_empty() {
+                    frame.render_widget(Paragraph::new("[No buses in the next 24 hours]"), body);
+                } else {
+                    self.draw_list(frame, body, items, *selected);
+                }
+                // The stop as opened, which `f` stars, not the station it may be part of.
+                let star = if self.favorites.contains(&self.agency, stop_id) {
+                    " ★"
+                } else {
+                    ""
+                };
+                (
+                    format!("{}{}", info.stop_name, star),
+                    "↑↓ move  enter trip  t timetable  f favorite  r refresh  / search  esc back  \
+                     q quit",
+                )
+            }
+
+            Screen::Trip { info, .. } => {
+                let items: Vec<_> = info
+                    .stops
+                    .iter()
+                    .map(|stop| {
+                        let predicted = if stop.delay.is_some_and(|delay| delay != 0.0) {
+                            stop.predicted_departure().map(time).unwrap_or_default()
+                        } else {
+                            String::new()
+                        };
+                        ListItem::new(format!(
+                            "{} {:8} {:8} {:10} {}",
+                            if stop.passed { "✓" } else { " " },
+                            stop.departure.map(time).unwrap_or_default(),
+                            predicted,
+                            print_status(stop.status, stop.delay),
+                            stop.stop_name,
+                        ))
+                    })
+                    .collect();
+                self.draw_list(frame, body, items, *selected);
+                (
+                    format!(
+                        "Route {} to {} ({})",
+                        info.route, info.headsign, info.service_day
+                    ),
+                    "↑↓ move  enter stop  t timetable  r refresh  / search  esc back  q quit",
+                )
+            }
+
+            Screen::Timetable { timetables, shown } => {
+                let timetable = &timetables[*shown];
+                let legend_height = (timetable.stops.len() as u16 + 1).min(body.height / 3);
+                let [legend, table] =
+                    Layout::vertical([Constraint::Length(legend_height), Constraint::Min(0)])
+                        .areas(body);
+
+                let stops: Vec<_> = timetable
+                    .stops
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, stop_name))| Line::from(format!("{:>4}  {}", i + 1, stop_name)))
+                    .collect();
+                frame.render_widget(
+                    Paragraph::new(stops).block(Block::default().borders(Borders::BOTTOM)),
+                    legend,
+                );
+
+                let header = Row::new(
+                    (1..=timetable.stops.len())
+                        .map(|i| format!("{:>8}", i))
+                        .chain(std::iter::once("Headsign".to_owned())),
+                )
+                .style(Style::default().add_modifier(Modifier::BOLD));
+                let rows = timetable.trips.iter().map(|trip| {
+                    Row::new(
+                        trip.times
+                            .iter()
+                            .map(|departure| {
+                                format!("{:>8}", departure.map(time).unwrap_or("-".into()))
+                            })
+                            .chain(std::iter::once(trip.headsign.clone())),
+                    )
+                });
+                let widths = std::iter::repeat_n(Constraint::Length(8), timetable.stops.len())
+                    .chain(std::iter::once(Constraint::Min(10)));
+                let mut state = TableState::default().with_selected(Some(*selected));
+                frame.render_stateful_widget(
+                    Table::new(rows, widths)
+                        .header(header)
+                        .row_highlight_style(highlight),
+                    table,
+                    &mut state,
+                );
+
+                (
+                    format!(
+                        "Route {} {} (direction {})",
+                        timetable.route_short_name,
+                        timetable.direction_name,
+                        timetable.direction_id
+                    ),
+                    "↑↓ move  tab direction  / search  esc back  q quit",
+                )
+            }
+        };
+
+        frame.render_widget(
+            Paragraph::new(heading).style(Style::default().add_modifier(Modifier::BOLD)),
+            title,
+        );
+
+        // Say how old the real-time data is, unless there is something more pressing.
+        let status = match (&self.message, &self.trip_update_url, self.real_time_age) {
+            (Some(message), _, _) => message.clone(),
+            (None, None, _) => "no real-time data".into(),
+            (None, Some(_), None) => "no real-time data yet".into(),
+            (None, Some(_), Some(made)) => format!(
+                "real-time data {} s old",
+                (Utc::now() - made).num_seconds().max(0)
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{}  │  {}", keys, status))
+                .style(Style::default().add_modifier(Modifier::DIM)),
+            footer,
+        );
+    }
+
+    fn draw_list(&self, frame: &mut Frame, area: Rect, items: Vec<ListItem>, selected: usize) {
+        let mut state = ListState::default().with_selected(Some(selected));
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            area,
+            &mut state,
+        );
+    }
+}
+
+/// The row of the first trip in the timetable that hasn't left its first stop yet.
+fn next_trip(timetable: &Timetable, now: NaiveDateTime) -> usize {
+    timetable
+        .trips
+        .iter()
+        .position(|trip| {
+            trip.times
+                .iter()
+                .flatten()
+                .next()
+                .is_some_and(|time| *time >= now)
+        })
+        .unwrap_or(0)
+}
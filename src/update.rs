@@ -0,0 +1,216 @@
+//! Downloading new schedule data, and rolling back to the previous data.
+//!
+//! Each download is kept as its own version next to the data, e.g. `~/.bus.20261017T083000`, and
+//! the data path itself is a symlink to the version in use. Switching versions re-points the link
+//! with a single rename, so a `bus` command run meanwhile sees either the old data or the new.
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path;
+
+use anyhow::{bail, Context};
+
+use chrono::{NaiveDateTime, Utc};
+
+use crate::Data;
+
+/// Suffix of the staging copy that older versions of `update` downloaded into.
+const STAGING_SUFFIX: &str = "new";
+
+/// Suffix of the link to the previous version of the data, kept around for `update --rollback`.
+const PREVIOUS_SUFFIX: &str = "old";
+
+/// Suffix of a link being made, before it is renamed over the real one.
+const LINK_SUFFIX: &str = "link";
+
+/// How versions are named after the time they were downloaded.
+const VERSION_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// The path next to the data with the given suffix, e.g. `~/.bus.old` for `~/.bus`.
+fn data_sibling(data: &str, suffix: &str) -> path::PathBuf {
+    path::PathBuf::from(format!("{}.{}", data.trim_end_matches('/'), suffix))
+}
+
+/// Is the data kept as a single GTFS zip rather than an extracted directory?
+fn is_zip_data(data: &str) -> bool {
+    path::Path::new(data).is_file() || data.ends_with(".zip")
+}
+
+fn is_link(path: &path::Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Remove a data directory, zip or link, if it exists. Links are removed, not followed.
+fn remove_data(data: &path::Path) -> io::Result<()> {
+    match fs::symlink_metadata(data) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(data),
+        Ok(_) => fs::remove_file(data),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Undo a failed step by removing what it left at `path`. The step's error is kept even if that
+/// fails too.
+fn clean_up(error: anyhow::Error, path: &path::Path) -> anyhow::Error {
+    match remove_data(path) {
+        Ok(()) => error,
+        Err(e) => error.context(format!("Also unable to remove {}: {}", path.display(), e)),
+    }
+}
+
+/// A path for a new version of the data, named after the current time.
+fn new_version(data: &str) -> path::PathBuf {
+    let stamp = Utc::now().format(VERSION_FORMAT).to_string();
+    let mut version = data_sibling(data, &stamp);
+    let mut n = 1;
+    while fs::symlink_metadata(&version).is_ok() {
+        version = data_sibling(data, &format!("{}-{}", stamp, n));
+        n += 1;
+    }
+    version
+}
+
+/// Is `path` one of the versions of `data`?
+fn is_version(data: &str, path: &path::Path) -> bool {
+    let prefix = data_sibling(data, "");
+    path.to_string_lossy()
+        .strip_prefix(&*prefix.to_string_lossy())
+        .and_then(|stamp| stamp.get(..15))
+        .is_some_and(|stamp| NaiveDateTime::parse_from_str(stamp, VERSION_FORMAT).is_ok())
+}
+
+/// The version a link points to, if it is a link.
+fn linked_version(link: &path::Path) -> Option<path::PathBuf> {
+    let target = fs::read_link(link).ok()?;
+    Some(match link.parent() {
+        Some(parent) => parent.join(target),
+        None => target,
+    })
+}
+
+/// Point `link` at `version` in one step, by making the new link beside it and renaming it over.
+fn point(link: &path::Path, version: &path::Path) -> Result<(), anyhow::Error> {
+    let new_link = data_sibling(&link.to_string_lossy(), LINK_SUFFIX);
+    remove_data(&new_link)?;
+    // Relative, so that the data can be moved along with its versions.
+    let target = version.file_name().context("Bad data path.")?;
+    symlink(target, &new_link).context("Unable to link to the schedule data.")?;
+    fs::rename(&new_link, link)
+        .context("Unable to switch the schedule data.")
+        .map_err(|e| clean_up(e, &new_link))
+}
+
+/// Data kept at `path` itself, as it was before versions, becomes a version with `path` linking
+/// to it. Until the link is made there is briefly nothing at `path`, but this only happens once.
+fn adopt(data: &str, path: &path::Path) -> Result<(), anyhow::Error> {
+    if is_link(path) || !path.exists() {
+        return Ok(());
+    }
+    let version = new_version(data);
+    fs::rename(path, &version).context("Unable to move aside the current data.")?;
+    point(path, &version)
+}
+
+/// Remove the versions of the data that neither the data nor the previous data link to.
+fn remove_unused(data: &str) -> Result<(), anyhow::Error> {
+    let in_use = [
+        linked_version(path::Path::new(data)),
+        linked_version(&data_sibling(data, PREVIOUS_SUFFIX)),
+    ];
+    let dir = path::Path::new(data)
+        .parent()
+        .unwrap_or(path::Path::new(""));
+    let listed = if dir.as_os_str().is_empty() {
+        path::Path::new(".")
+    } else {
+        dir
+    };
+    for entry in fs::read_dir(listed)? {
+        let version = dir.join(entry?.file_name());
+        if is_version(data, &version) && !in_use.iter().flatten().any(|used| *used == version) {
+            remove_data(&version)?;
+        }
+    }
+    Ok(())
+}
+
+/// Download the schedule data into `staging`, in the same form as `data`, and make sure that it
+/// loads.
+fn stage(data: &str, gtfs_url: &str, staging: &path::Path) -> Result<(), anyhow::Error> {
+    // download data.
+    let mut zipped = Vec::new();
+    reqwest::blocking::get(gtfs_url)
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.copy_to(&mut zipped))
+        .context("Unable to download schedule data.")?;
+
+    // unzip into staging.
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(&zipped))
+        .context("Downloaded schedule data is not a valid zip archive.")?;
+    if is_zip_data(data) {
+        fs::write(staging, &zipped).context("Unable to write schedule data.")?;
+    } else {
+        archive
+            .extract(staging)
+            .context("Unable to unzip downloaded schedule data.")?;
+    }
+
+    // make sure we can actually use it before replacing what we have.
+    Data::read(&staging.to_string_lossy())
+        .context("Downloaded schedule data is unusable. Keeping the current data.")?;
+
+    Ok(())
+}
+
+/// Download the latest schedule data into a new version next to the current data, check that it
+/// actually loads, and only then switch the data link to it. The current version is kept for
+/// `--rollback`, and older ones are removed.
+pub fn update(data: &str, gtfs_url: &str) -> Result<(), anyhow::Error> {
+    let data = data.trim_end_matches('/');
+    let data_path = path::Path::new(data);
+    let previous = data_sibling(data, PREVIOUS_SUFFIX);
+
+    if let Some(parent) = data_path.parent() {
+        fs::create_dir_all(parent).context("Unable to create data directory.")?;
+    }
+    // clean up after older versions of `update`.
+    remove_data(&data_sibling(data, STAGING_SUFFIX))?;
+    adopt(data, data_path)?;
+    adopt(data, &previous)?;
+
+    let version = new_version(data);
+    stage(data, gtfs_url, &version).map_err(|e| clean_up(e, &version))?;
+
+    let current = linked_version(data_path);
+    point(data_path, &version).map_err(|e| clean_up(e, &version))?;
+    if let Some(current) = current {
+        point(&previous, &current)?;
+    }
+
+    remove_unused(data).context("Unable to remove old schedule data.")
+}
+
+/// Switch the data back to the version from before the last update. The version switched away
+/// from becomes the previous one, so a second rollback undoes the first.
+pub fn rollback(data: &str) -> Result<(), anyhow::Error> {
+    let data = data.trim_end_matches('/');
+    let data_path = path::Path::new(data);
+    let previous = data_sibling(data, PREVIOUS_SUFFIX);
+
+    adopt(data, data_path)?;
+    adopt(data, &previous)?;
+
+    let version = match linked_version(&previous) {
+        Some(version) if version.exists() => version,
+        _ => bail!("No previous schedule data to roll back to."),
+    };
+    let current = linked_version(data_path);
+    point(data_path, &version)?;
+    if let Some(current) = current {
+        point(&previous, &current)?;
+    }
+
+    Ok(())
+}
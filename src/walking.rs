@@ -0,0 +1,131 @@
+//! Walking between stops: the transfers the feed lists, plus short walks to nearby stops.
+
+use anyhow::bail;
+
+use serde::Deserialize;
+
+use crate::gtfs::{Stop, TransferType};
+use crate::schedule::Data;
+
+/// How far and how fast riders are willing to walk between stops.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Walking {
+    /// The farthest to walk between two stops, in meters as the crow flies.
+    pub radius: f64,
+    /// In meters per second.
+    pub speed: f64,
+}
+
+impl Default for Walking {
+    fn default() -> Self {
+        Self {
+            radius: 400.0,
+            speed: 1.2,
+        }
+    }
+}
+
+impl Walking {
+    /// Make sure the walks can be timed: a radius of zero or more, walked at some speed within a
+    /// day.
+    pub fn check(&self) -> Result<(), anyhow::Error> {
+        if !(self.speed > 0.0 && self.speed.is_finite()) {
+            bail!("Walking speed must be more than 0, not {}", self.speed);
+        }
+        if !(self.radius >= 0.0 && self.radius.is_finite()) {
+            bail!("Walking radius must be 0 or more, not {}", self.radius);
+        }
+        if self.radius / self.speed > 86400.0 {
+            bail!(
+                "Walking {} m at {} m/s takes over a day",
+                self.radius,
+                self.speed
+            );
+        }
+        Ok(())
+    }
+
+    /// How long it takes to walk the given distance in meters.
+    pub fn time(&self, distance: f64) -> chrono::Duration {
+        chrono::Duration::seconds((distance / self.speed).ceil() as i64)
+    }
+}
+
+/// A walk from one stop to another, from [`Data::footpaths`].
+#[derive(Debug, Clone)]
+pub struct Footpath {
+    pub to_stop_id: String,
+    pub duration: chrono::Duration,
+    /// In meters, if both stops have locations.
+    pub distance: Option<f64>,
+    /// Whether the walk comes from `transfers.txt` rather than from the stops being close.
+    pub explicit: bool,
+}
+
+impl Data {
+    /// The walks from the given stop, shortest first. These are the transfers listed in
+    /// `transfers.txt`, plus walks to every other stop within [`Data::walking`]'s radius. A
+    /// transfer from a stop to itself gives the time needed to change buses there. Transfers that
+    /// are marked not possible rule out walking between those stops altogether.
+    pub fn footpaths(&self, stop_id: &str) -> Vec<Footpath> {
+        let from = self.stops.get(stop_id).and_then(Stop::location);
+        let transfers = self
+            .transfers
+            .get(stop_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut footpaths: Vec<_> = transfers
+            .iter()
+            .filter(|transfer| transfer.transfer_type != TransferType::NotPossible)
+            .map(|transfer| {
+                let distance = from
+                    .zip(
+                        self.stops
+                            .get(&transfer.to_stop_id)
+                            .and_then(Stop::location),
+                    )
+                    .map(|(from, to)| from.distance_to(to));
+                let duration = match (transfer.transfer_type, transfer.min_transfer_time) {
+                    (TransferType::MinimumTime, Some(seconds)) => {
+                        chrono::Duration::seconds(seconds as i64)
+                    }
+                    _ if transfer.to_stop_id == stop_id => chrono::Duration::zero(),
+                    _ => distance.map_or_else(chrono::Duration::zero, |distance| {
+                        self.walking.time(distance)
+                    }),
+                };
+
+                Footpath {
+                    to_stop_id: transfer.to_stop_id.clone(),
+                    duration,
+                    distance,
+                    explicit: true,
+                }
+            })
+            .collect();
+
+        if let Some(from) = from {
+            for (to_stop_id, distance) in self.stop_grid.within(from, self.walking.radius) {
+                if to_stop_id == stop_id
+                    || transfers
+                        .iter()
+                        .any(|transfer| &transfer.to_stop_id == to_stop_id)
+                {
+                    continue;
+                }
+
+                footpaths.push(Footpath {
+                    to_stop_id: to_stop_id.clone(),
+                    duration: self.walking.time(distance),
+                    distance: Some(distance),
+                    explicit: false,
+                });
+            }
+        }
+
+        footpaths.sort_by_key(|footpath| footpath.duration);
+        footpaths
+    }
+}
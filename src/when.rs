@@ -0,0 +1,192 @@
+//! Reading the times people type, like `07:30`, `tomorrow 8am`, `+20m` or `sat`, and the time
+//! zones they may be in.
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use chrono_tz::Tz;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("monday", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+];
+
+/// Parse a time relative to `now`. This may be
+///
+/// - a time of day, on a 24-hour (`07:30`) or 12-hour (`8am`, `5:15 pm`) clock, or `noon`;
+/// - a day: `today`, `tomorrow`, a weekday like `sat` or `saturday` (the next one, or today), or
+///   a date like `2026-10-24`;
+/// - a day followed by a time of day, like `tomorrow 8am`;
+/// - or a while from now, like `+20m`, `+1h30m`, `+1h30` or `+90` (in minutes).
+///
+/// A time of day alone is on `now`'s day. A day alone means from the start of that day, or from
+/// `now` if it is today.
+pub fn parse(when: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let when = when.trim().to_lowercase();
+    if let Some(offset) = when.strip_prefix('+') {
+        return now.checked_add_signed(parse_offset(offset)?);
+    }
+
+    let (day, time) = match when.split_once(char::is_whitespace) {
+        Some((first, rest)) => match parse_day(first, now.date()) {
+            Some(day) => (Some(day), Some(rest)),
+            None => (None, Some(when.as_str())),
+        },
+        None => match parse_day(&when, now.date()) {
+            Some(day) => (Some(day), None),
+            None => (None, Some(when.as_str())),
+        },
+    };
+
+    match (day, time) {
+        (day, Some(time)) => Some(day.unwrap_or(now.date()).and_time(parse_time(time)?)),
+        (Some(day), None) if day == now.date() => Some(now),
+        (Some(day), None) => day.and_hms_opt(0, 0, 0),
+        (None, None) => None,
+    }
+}
+
+/// A while like `20m`, `1h30m`, `1h30`, `2h` or `90`. A number without a unit at the end is in
+/// minutes.
+fn parse_offset(offset: &str) -> Option<chrono::Duration> {
+    let offset: String = offset.chars().filter(|c| !c.is_whitespace()).collect();
+    if offset.is_empty() {
+        return None;
+    }
+
+    let mut total = chrono::Duration::zero();
+    let mut rest = offset.as_str();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number: i64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let duration = match &rest[..unit] {
+            "d" | "day" | "days" => chrono::Duration::try_days(number),
+            "h" | "hr" | "hrs" | "hour" | "hours" => chrono::Duration::try_hours(number),
+            "" | "m" | "min" | "mins" | "minute" | "minutes" => {
+                chrono::Duration::try_minutes(number)
+            }
+            _ => return None,
+        }?;
+        total = total.checked_add(&duration)?;
+        rest = &rest[unit..];
+    }
+    Some(total)
+}
+
+/// A day like `today`, `tomorrow`, `sat` or `2026-10-24`.
+fn parse_day(day: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match day {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    // Any abbreviation of at least three letters, like `sat` or `thurs`.
+    let weekday = WEEKDAYS
+        .iter()
+        .find(|(name, _)| day.len() >= 3 && name.starts_with(day))
+        .map(|(_, weekday)| *weekday)?;
+    let days_ahead =
+        (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    Some(today + chrono::Duration::days(days_ahead as i64))
+}
+
+/// A time of day like `07:30`, `8am`, `5:15 pm` or `noon`.
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    let time: String = time.chars().filter(|c| !c.is_whitespace()).collect();
+    match time.as_str() {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (clock, afternoon) =
+        if let Some(clock) = time.strip_suffix("am").or_else(|| time.strip_suffix('a')) {
+            (clock, Some(false))
+        } else if let Some(clock) = time.strip_suffix("pm").or_else(|| time.strip_suffix('p')) {
+            (clock, Some(true))
+        } else {
+            (time.as_str(), None)
+        };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        Some(_) => return None,
+        // Only a 12-hour clock may leave out the minutes.
+        None if afternoon.is_some() => (clock.parse().ok()?, 0),
+        None => return None,
+    };
+    let hour: u32 = match afternoon {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// The zone this computer is set to, or UTC if it can't be found.
+pub fn local_timezone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Parse an IANA zone name like `America/Chicago` or `UTC`, or `local` for this computer's zone.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    if name.eq_ignore_ascii_case("local") {
+        Some(local_timezone())
+    } else {
+        name.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 16)
+            .unwrap()
+            .and_hms_opt(18, 0, 0)
+            .unwrap()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(2026, 10, day)?.and_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse("+20m", now()), at(16, 18, 20));
+        assert_eq!(parse("+90", now()), at(16, 19, 30));
+        assert_eq!(parse("+1h30m", now()), at(16, 19, 30));
+        assert_eq!(parse("+1h30", now()), at(16, 19, 30));
+        assert_eq!(parse("+1 day", now()), at(17, 18, 0));
+        assert_eq!(parse("+", now()), None);
+        assert_eq!(parse("+h", now()), None);
+        assert_eq!(parse("+20x", now()), None);
+    }
+
+    #[test]
+    fn offsets_too_far_away() {
+        assert_eq!(parse("+99999999999999", now()), None);
+        assert_eq!(parse("+99999999999999d", now()), None);
+        assert_eq!(parse("+9223372036854775807m", now()), None);
+        assert_eq!(parse("+99999999999999999999", now()), None);
+    }
+}
@@ -0,0 +1,7 @@
+
+
+2.0 
+1
+
+
+X1 *R3"A
\ No newline at end of file
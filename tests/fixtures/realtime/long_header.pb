@@ -0,0 +1,6 @@
+
+{
+s2.0----------------------------------------------------------------------------------------------------------------
+1
+	
+T1*R80"100
\ No newline at end of file
@@ -0,0 +1,6 @@
+
+
+2.0'
+1"
+
+R1a	<"Ax"B"C
\ No newline at end of file
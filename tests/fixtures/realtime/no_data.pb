@@ -0,0 +1,9 @@
+
+
+2.0 
+1
+
+R1a	<"A"B(
+2
+
+R1b<"A"B(
\ No newline at end of file
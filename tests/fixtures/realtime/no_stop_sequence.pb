@@ -0,0 +1,6 @@
+
+
+2.0
+1
+
+R1aZ"A
\ No newline at end of file
@@ -0,0 +1,15 @@
+
+
+2.03
+1.
+	
+T1*R80"100<"102)
+2$
+	
+T5*R80"100	"101(
+3
+
+T3 *R800
+4+
+
+X9 *R80"101Ī"102
\ No newline at end of file
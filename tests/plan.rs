@@ -0,0 +1,170 @@
+//! Planning journeys on a small made-up feed.
+//!
+//! Stops A to E lie on a line, with F a short walk from D and the night bus running D, G, H past
+//! midnight. From A to D there is a slow direct bus (3), or route 1 to B or C and a change there.
+//! Changing buses takes 5 minutes at B and 2 at C. Route 4 passes through E without stopping.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use bus::{Data, Journey, PlanTime};
+
+fn data() -> Data {
+    Data::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gtfs")).unwrap()
+}
+
+fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2026, 10, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+/// The trips ridden, with walks as `walk`.
+fn trips(journey: &Journey) -> Vec<&str> {
+    journey
+        .legs
+        .iter()
+        .map(|leg| {
+            if leg.walking {
+                "walk"
+            } else {
+                leg.trip_id.as_str()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn depart_finds_fastest_journey_for_each_number_of_transfers() {
+    let journeys = data()
+        .plan("A", "D", PlanTime::Depart(at(16, 7, 55)))
+        .unwrap();
+
+    assert_eq!(journeys.len(), 2);
+    assert_eq!(trips(&journeys[0]), ["R3a"]);
+    assert_eq!(journeys[0].transfers(), 0);
+    assert_eq!(journeys[0].departure(), at(16, 8, 5));
+    assert_eq!(journeys[0].arrival(), at(16, 9, 0));
+
+    // Off at B at 8:10 gives the 5 minutes needed to make the 8:20 on route 4.
+    assert_eq!(trips(&journeys[1]), ["R1a", "R4a"]);
+    assert_eq!(journeys[1].transfers(), 1);
+    assert_eq!(journeys[1].legs[0].to_stop_id, "B");
+    assert_eq!(journeys[1].legs[1].departure, at(16, 8, 20));
+    assert_eq!(journeys[1].arrival(), at(16, 8, 35));
+}
+
+#[test]
+fn arrive_by_finds_latest_journey_for_each_number_of_transfers() {
+    let journeys = data()
+        .plan("A", "D", PlanTime::ArriveBy(at(16, 9, 15)))
+        .unwrap();
+
+    assert_eq!(journeys.len(), 2);
+    assert_eq!(trips(&journeys[0]), ["R3a"]);
+    assert_eq!(journeys[0].departure(), at(16, 8, 5));
+
+    // Off at C at 8:50 leaves the 2 minutes needed for the 8:55 on route 2.
+    assert_eq!(trips(&journeys[1]), ["R1b", "R2b"]);
+    assert_eq!(journeys[1].transfers(), 1);
+    assert_eq!(journeys[1].departure(), at(16, 8, 30));
+    assert_eq!(journeys[1].arrival(), at(16, 9, 10));
+}
+
+#[test]
+fn arrive_by_leaves_out_journeys_that_are_too_late() {
+    let data = data();
+    let journeys = data
+        .plan("A", "D", PlanTime::ArriveBy(at(16, 8, 45)))
+        .unwrap();
+
+    // Today's direct bus gets in at 9:00, and yesterday's is too long before to be worth taking.
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(journeys[0].transfers(), 1);
+    assert_eq!(journeys[0].departure(), at(16, 8, 0));
+    assert!(journeys[0].arrival() <= at(16, 8, 45));
+
+    // Nothing gets in before 8:35 today.
+    assert!(data
+        .plan("A", "D", PlanTime::ArriveBy(at(16, 7, 0)))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn depart_leaves_out_journeys_that_are_too_late() {
+    // The last bus has gone, and tomorrow's first is too far off.
+    assert!(data()
+        .plan("A", "D", PlanTime::Depart(at(16, 10, 0)))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn more_transfers_only_if_faster() {
+    // Route 1 and then route 2 would also get there, but later than the direct bus.
+    let journeys = data()
+        .plan("A", "D", PlanTime::Depart(at(16, 8, 1)))
+        .unwrap();
+
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(trips(&journeys[0]), ["R3a"]);
+}
+
+#[test]
+fn walks_to_a_nearby_stop() {
+    let journeys = data()
+        .plan("A", "F", PlanTime::Depart(at(16, 7, 55)))
+        .unwrap();
+
+    assert_eq!(journeys.len(), 2);
+    assert_eq!(trips(&journeys[0]), ["R3a", "walk"]);
+    assert_eq!(trips(&journeys[1]), ["R1a", "R4a", "walk"]);
+    assert_eq!(journeys[1].transfers(), 1);
+
+    let walk = journeys[1].legs.last().unwrap();
+    assert_eq!(walk.from_stop_id, "D");
+    assert_eq!(walk.to_stop_id, "F");
+    assert_eq!(walk.departure, at(16, 8, 35));
+    assert!(walk.arrival > walk.departure && walk.arrival < at(16, 8, 36));
+}
+
+#[test]
+fn no_drop_off_or_pickup() {
+    let data = data();
+
+    // Route 4 is the only bus through E, and it neither lets anyone off nor on there.
+    assert!(data
+        .plan("B", "E", PlanTime::Depart(at(16, 8, 0)))
+        .unwrap()
+        .is_empty());
+    assert!(data
+        .plan("E", "D", PlanTime::Depart(at(16, 8, 0)))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn trip_past_midnight() {
+    let data = data();
+
+    let journeys = data
+        .plan("D", "H", PlanTime::Depart(at(16, 23, 40)))
+        .unwrap();
+    assert_eq!(journeys.len(), 1);
+    assert_eq!(trips(&journeys[0]), ["N1"]);
+    assert_eq!(journeys[0].departure(), at(16, 23, 50));
+    assert_eq!(journeys[0].arrival(), at(17, 0, 30));
+
+    // After midnight, the night bus is still running on the day before's service.
+    let journeys = data.plan("G", "H", PlanTime::Depart(at(17, 0, 5))).unwrap();
+    assert_eq!(trips(&journeys[0]), ["N1"]);
+    assert_eq!(journeys[0].departure(), at(17, 0, 10));
+    assert_eq!(journeys[0].arrival(), at(17, 0, 30));
+
+    let journeys = data
+        .plan("D", "H", PlanTime::ArriveBy(at(17, 0, 45)))
+        .unwrap();
+    assert_eq!(trips(&journeys[0]), ["N1"]);
+    assert_eq!(journeys[0].departure(), at(16, 23, 50));
+}
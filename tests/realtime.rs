@@ -0,0 +1,153 @@
+//! Decoding recorded GTFS-RT trip update feeds.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+
+use bus::realtime::{parse_real_time_data, parse_real_time_feed};
+use bus::{Data, FilterConfig, RealTimeInfo};
+
+fn read_fixture(name: &str) -> RealTimeInfo {
+    let path = format!(
+        "{}/tests/fixtures/realtime/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    let raw = std::fs::read(&path).unwrap();
+    parse_real_time_data(&parse_real_time_feed(&raw).unwrap())
+}
+
+#[test]
+fn delays_by_stop() {
+    let real_time = read_fixture("trip_updates.pb");
+
+    let expected: HashMap<String, HashMap<String, f64>> = [
+        ("100", vec![("T1", -120.0), ("T5", 180.0)]),
+        ("102", vec![("T1", 60.0)]),
+    ]
+    .iter()
+    .map(|(stop_id, trips)| {
+        let trips = trips
+            .iter()
+            .map(|(trip_id, delay)| (trip_id.to_string(), *delay))
+            .collect();
+        (stop_id.to_string(), trips)
+    })
+    .collect();
+    assert_eq!(real_time.by_stop, expected);
+
+    // Stop 101 has no update of its own, so it gets the delay from the stop before.
+    assert_eq!(real_time.delay("T1", "101", Some(2)), Some(-120.0));
+    assert_eq!(real_time.delay("T1", "102", Some(3)), Some(60.0));
+}
+
+#[test]
+fn cancelled_skipped_and_added() {
+    let real_time = read_fixture("trip_updates.pb");
+
+    assert_eq!(real_time.cancelled.len(), 1);
+    assert!(real_time.cancelled.contains("T3"));
+    assert!(real_time
+        .by_stop
+        .values()
+        .all(|trips| !trips.contains_key("T3")));
+
+    assert_eq!(real_time.skipped.len(), 1);
+    assert!(real_time
+        .skipped
+        .contains(&("T5".to_string(), "101".to_string())));
+
+    let added: Vec<_> = ["101", "102"]
+        .iter()
+        .map(|stop_id| {
+            let stops = &real_time.added[*stop_id];
+            assert_eq!(stops.len(), 1);
+            assert_eq!(stops[0].trip_id, "X9");
+            assert_eq!(stops[0].route_id.as_deref(), Some("R80"));
+            stops[0].departure.timestamp()
+        })
+        .collect();
+    assert_eq!(added, vec![1_792_152_600, 1_792_152_900]);
+    assert_eq!(real_time.added.len(), 2);
+}
+
+#[test]
+fn header_timestamp() {
+    let real_time = read_fixture("trip_updates.pb");
+    assert_eq!(
+        real_time.timestamp,
+        Some(Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap())
+    );
+}
+
+#[test]
+fn protobuf_that_looks_like_json() {
+    // The header is 123 bytes long, so the feed starts with "\n{".
+    let real_time = read_fixture("long_header.pb");
+    assert_eq!(real_time.delay("T1", "100", Some(1)), Some(30.0));
+}
+
+#[test]
+fn json_with_leading_whitespace() {
+    let raw = b"\n  {\"header\": {\"timestamp\": 1792152000}, \"entity\": [{\"trip_update\": \
+        {\"trip\": {\"trip_id\": \"T1\"}, \"stop_time_update\": [{\"stop_sequence\": 1, \
+        \"stop_id\": \"100\", \"departure\": {\"delay\": 45}}]}}]}";
+    let real_time = parse_real_time_data(&parse_real_time_feed(raw).unwrap());
+    assert_eq!(real_time.delay("T1", "100", Some(1)), Some(45.0));
+    assert!(real_time.timestamp.is_some());
+}
+
+#[test]
+fn carry_forward_without_stop_sequence() {
+    // Trip R1a runs A, B, C, and the feed only says it is 90 seconds late at A.
+    let real_time = read_fixture("no_stop_sequence.pb");
+    let data = Data::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gtfs")).unwrap();
+
+    assert_eq!(real_time.delay("R1a", "B", Some(2)), None);
+    assert_eq!(
+        data.real_time_delay(&real_time, "R1a", "A", Some(1)),
+        Some(90.0)
+    );
+    assert_eq!(
+        data.real_time_delay(&real_time, "R1a", "C", Some(3)),
+        Some(90.0)
+    );
+
+    let after = NaiveDate::from_ymd_opt(2026, 10, 16)
+        .unwrap()
+        .and_hms_opt(7, 0, 0)
+        .unwrap();
+    let filter = FilterConfig::new("B").after(after).how_many(1).route("1");
+    let buses = data.stop_sched(filter, &real_time).unwrap().buses;
+    assert_eq!(buses[0].trip_id, "R1a");
+    assert_eq!(buses[0].delay, Some(90.0));
+}
+
+#[test]
+fn no_delay_after_no_data() {
+    // Trips R1a and R1b run A, B, C, and the feed is 60 seconds late at A but has no data from
+    // B on, given by stop_sequence for R1a and by stop alone for R1b.
+    let real_time = read_fixture("no_data.pb");
+    let data = Data::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gtfs")).unwrap();
+
+    assert_eq!(real_time.delay("R1a", "A", Some(1)), Some(60.0));
+    assert_eq!(real_time.delay("R1a", "B", Some(2)), None);
+    assert_eq!(real_time.delay("R1a", "C", Some(3)), None);
+
+    assert_eq!(
+        data.real_time_delay(&real_time, "R1b", "A", Some(1)),
+        Some(60.0)
+    );
+    assert_eq!(data.real_time_delay(&real_time, "R1b", "B", Some(2)), None);
+    assert_eq!(data.real_time_delay(&real_time, "R1b", "C", Some(3)), None);
+}
+
+#[test]
+fn feed_order_without_every_stop_sequence() {
+    // Trip R1a runs A, B, C. The feed gives the stop_sequence of A and C but not of B, so B
+    // stays between them and its delay carries on to C.
+    let real_time = read_fixture("mixed_stop_sequence.pb");
+    assert_eq!(real_time.delay("R1a", "A", Some(1)), Some(60.0));
+    assert_eq!(real_time.delay("R1a", "B", Some(2)), Some(120.0));
+    assert_eq!(real_time.delay("R1a", "C", Some(3)), Some(120.0));
+}
@@ -0,0 +1,78 @@
+//! Reading a feed that has only the columns the GTFS spec requires. It also has a trip on a
+//! service that isn't in the calendar (T3), stop times for a trip that isn't there (T9), and a
+//! route with no trips (3).
+
+use chrono::NaiveDate;
+
+use bus::realtime::{parse_real_time_data, parse_real_time_feed};
+use bus::{Data, FilterConfig, RealTimeInfo};
+
+fn data() -> Data {
+    Data::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/gtfs-minimal"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn route_names_come_from_routes() {
+    let data = data();
+    assert_eq!(data.trips["T1"].route_short_name, "1");
+    // Without a short name, the long name stands in.
+    assert_eq!(data.trips["T2"].route_short_name, "Crosstown");
+}
+
+#[test]
+fn buses_at_a_stop() {
+    let after = NaiveDate::from_ymd_opt(2026, 10, 16)
+        .unwrap()
+        .and_hms_opt(7, 0, 0)
+        .unwrap();
+    let filter = FilterConfig::new("A").after(after).how_many(1).route("1");
+    let buses = data()
+        .stop_sched(filter, &RealTimeInfo::default())
+        .unwrap()
+        .buses;
+
+    assert_eq!(buses.len(), 1);
+    assert_eq!(buses[0].trip_id, "T1");
+    assert_eq!(buses[0].route, "1");
+    assert_eq!(buses[0].departure, after + chrono::Duration::hours(1));
+}
+
+#[test]
+fn leaves_out_orphans() {
+    let data = data();
+    assert!(!data.trips.contains_key("T3"));
+    assert!(data
+        .stop_times
+        .values()
+        .flatten()
+        .all(|stop_time| stop_time.trip_id == "T1" || stop_time.trip_id == "T2"));
+
+    let day = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+    assert_eq!(data.routes_on(day).len(), 2);
+}
+
+#[test]
+fn added_trip_on_a_route_without_trips() {
+    let raw = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/realtime/added_trip.pb"
+    ))
+    .unwrap();
+    let real_time = parse_real_time_data(&parse_real_time_feed(&raw).unwrap());
+
+    let after = NaiveDate::from_ymd_opt(2026, 10, 16)
+        .unwrap()
+        .and_hms_opt(7, 0, 0)
+        .unwrap();
+    let filter = FilterConfig::new("A").after(after).route("3");
+    let buses = data().stop_sched(filter, &real_time).unwrap().buses;
+
+    assert_eq!(buses.len(), 1);
+    assert_eq!(buses[0].trip_id, "X1");
+    assert_eq!(buses[0].route, "3");
+    assert_eq!(buses[0].departure, after + chrono::Duration::minutes(90));
+}